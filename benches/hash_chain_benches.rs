@@ -1,21 +1,12 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use hash_chain::HashChain;
-use plonky2::{
-    field::goldilocks_field::GoldilocksField,
-    plonk::{
-        circuit_builder::CircuitBuilder,
-        circuit_data::CircuitConfig,
-        config::{GenericConfig, PoseidonGoldilocksConfig},
-    },
-};
+use hash_chain::{HashChainBuilder, SegmentedProver};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
 fn hash_chain_proving_benchmark(c: &mut Criterion) {
     const D: usize = 2;
     type C = PoseidonGoldilocksConfig;
     type F = <C as GenericConfig<D>>::F;
 
-    let config = CircuitConfig::standard_recursion_config();
-
     // Power of two recursive step sizes
     let step_sizes = [2, 4, 8, 16, 32, 64];
 
@@ -26,24 +17,70 @@ fn hash_chain_proving_benchmark(c: &mut Criterion) {
     for &steps in &step_sizes {
         group.bench_function(format!("hash_chain_{}_steps", steps), |b| {
             b.iter(|| {
-                let mut circuit = black_box(CircuitBuilder::<F, D>::new(config.clone()));
-                let (_, _) = <CircuitBuilder<GoldilocksField, D> as HashChain<
-                    GoldilocksField,
-                    D,
-                    C,
-                >>::build_hash_chain_circuit(&mut circuit, steps)
-                .unwrap();
+                let (prover, _) = black_box(
+                    HashChainBuilder::<F, C, D>::new()
+                        .steps(steps)
+                        .build()
+                        .unwrap(),
+                );
+                black_box(prover.prove_chain().unwrap());
             });
         });
     }
 }
 
-fn hash_chain_verification_benchmark(c: &mut Criterion) {
+fn hash_chain_reused_prover_benchmark(c: &mut Criterion) {
     const D: usize = 2;
     type C = PoseidonGoldilocksConfig;
     type F = <C as GenericConfig<D>>::F;
 
-    let config = CircuitConfig::standard_recursion_config();
+    // Power of two recursive step sizes
+    let step_sizes = [2, 4, 8, 16, 32, 64];
+
+    let mut group = c.benchmark_group("HashChain Reused Prover");
+
+    // Configure the group
+    group.sample_size(10);
+
+    // The circuit is built once, outside of the timed block, so only proving is timed
+    let (prover, _) = HashChainBuilder::<F, C, D>::new().build().unwrap();
+    for &steps in &step_sizes {
+        group.bench_function(format!("hash_chain_{}_steps", steps), |b| {
+            b.iter(|| {
+                black_box(prover.prove(Default::default(), steps).unwrap());
+            });
+        });
+    }
+}
+
+fn hash_chain_segmented_prover_benchmark(c: &mut Criterion) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    // Power of two recursive step sizes
+    let step_sizes = [8, 16, 32, 64];
+
+    let mut group = c.benchmark_group("HashChain Segmented Prover");
+
+    // Configure the group
+    group.sample_size(10);
+
+    // The segment and aggregation circuits are built once, outside of the timed block
+    let prover = SegmentedProver::<F, C, D>::new(8).unwrap();
+    for &steps in &step_sizes {
+        group.bench_function(format!("hash_chain_{}_steps", steps), |b| {
+            b.iter(|| {
+                black_box(prover.prove(Default::default(), steps).unwrap());
+            });
+        });
+    }
+}
+
+fn hash_chain_verification_benchmark(c: &mut Criterion) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
 
     let mut group = c.benchmark_group("HashChain Verifier");
 
@@ -56,22 +93,15 @@ fn hash_chain_verification_benchmark(c: &mut Criterion) {
     for &steps in &step_sizes {
         group.bench_function(format!("hash_chain_verify_{}_steps", steps), |b| {
             // Move the circuit and proof generation out of the iterated benchmark block
-            let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
-            let (proof, circuit_map) = <CircuitBuilder<GoldilocksField, D> as HashChain<
-                GoldilocksField,
-                D,
-                C,
-            >>::build_hash_chain_circuit(&mut circuit, steps)
-            .unwrap();
+            let (prover, verifier) = HashChainBuilder::<F, C, D>::new()
+                .steps(steps)
+                .build()
+                .unwrap();
+            let proof = prover.prove_chain().unwrap();
 
             b.iter(|| {
                 // Only verification is timed
-                let verification_result =
-                    black_box(<CircuitBuilder<GoldilocksField, D> as HashChain<
-                        GoldilocksField,
-                        D,
-                        C,
-                    >>::verify(proof.clone(), &circuit_map));
+                let verification_result = black_box(verifier.verify(proof.clone()));
 
                 verification_result.unwrap();
                 black_box(());
@@ -83,6 +113,8 @@ fn hash_chain_verification_benchmark(c: &mut Criterion) {
 criterion_group!(
     benches,
     hash_chain_proving_benchmark,
+    hash_chain_reused_prover_benchmark,
+    hash_chain_segmented_prover_benchmark,
     hash_chain_verification_benchmark
 );
 criterion_main!(benches);
@@ -1,18 +1,10 @@
-use hash_chain::HashChain;
+use hash_chain::{vdf_eval, HashChainBuilder, HashChainProver, PoseidonHasher};
 use log::info;
-use plonky2::{
-    field::goldilocks_field::GoldilocksField,
-    plonk::{
-        circuit_builder::CircuitBuilder,
-        circuit_data::CircuitConfig,
-        config::{GenericConfig, PoseidonGoldilocksConfig},
-    },
-};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 use std::{env, time::Instant};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let verbose = args.contains(&"-vv".to_string());
     if env::args().any(|arg| arg == "-vv") {
         std::env::set_var("RUST_LOG", "info");
     }
@@ -27,37 +19,62 @@ fn main() {
         }
     }
 
+    // Hashes applied by each recursion layer, 1 if not specified
+    let mut hashes_per_layer = 1;
+    if let Some(pos) = args.iter().position(|x| x == "--hashes-per-layer") {
+        if let Some(hashes_arg) = args.get(pos + 1) {
+            hashes_per_layer = hashes_arg
+                .parse()
+                .expect("Invalid number for hashes per layer");
+        }
+    }
+
     const D: usize = 2;
     type C = PoseidonGoldilocksConfig;
     type F = <C as GenericConfig<D>>::F;
 
-    let config = CircuitConfig::standard_recursion_config();
-    let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
-    // Timing verification
-    let start_time = Instant::now();
-    let (proof, circuit_map) = <CircuitBuilder<GoldilocksField, D> as HashChain<
-        GoldilocksField,
-        D,
-        C,
-    >>::build_hash_chain_circuit(&mut circuit, steps)
-    .expect("Failed to build hash chain circuit");
-    let proof_time = start_time.elapsed();
+    // In VDF mode, compare the sequential evaluations against proving them
+    if args.contains(&"--vdf".to_string()) {
+        let prover = HashChainProver::<F, C, D>::new().expect("Failed to build VDF circuit");
+        let evaluation =
+            vdf_eval(&prover, b"telemetry challenge", steps).expect("Failed to evaluate VDF");
+        let report = serde_json::json!({
+            "steps": steps,
+            "sequential_time": evaluation.sequential_time,
+            "proving_time": evaluation.proving_time,
+            "proving_overhead": evaluation.proving_time.as_secs_f64()
+                / evaluation.sequential_time.as_secs_f64().max(f64::EPSILON),
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Failed to serialize VDF report")
+        );
+        return;
+    }
+
+    // Build the circuit and prove the chain, gathering telemetry along the way
+    let (prover, verifier) = HashChainBuilder::<F, C, D>::new()
+        .steps(steps)
+        .hashes_per_layer(hashes_per_layer)
+        .build()
+        .expect("Failed to build hash chain circuit");
+    let (proof, telemetry) = prover
+        .prove_chain_with_telemetry()
+        .expect("Failed to prove hash chain");
 
     // Timing verification
     let start_time = Instant::now();
-    let verification_result = <CircuitBuilder<GoldilocksField, D> as HashChain<
-        GoldilocksField,
-        D,
-        C,
-    >>::verify(proof, &circuit_map);
+    let verification_result = verifier.verify_steps::<PoseidonHasher>(proof, steps as u64);
     let verify_time = start_time.elapsed();
 
-    // Ensure the verification is successful before considering timing
+    // Ensure the verification is successful before reporting anything
     assert!(verification_result.is_ok(), "Verification failed");
 
-    if verbose {
-        info!("Proof time: {:?}", proof_time);
-        info!("Verification time: {:?}", verify_time);
-        info!("Circuit depth: {}", steps);
-    }
+    info!("Proof time: {:?}", telemetry.proving_time());
+    info!("Verification time: {:?}", verify_time);
+    info!("Throughput: {:.1} hashes/s", telemetry.hashes_per_second());
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&telemetry).expect("Failed to serialize telemetry")
+    );
 }
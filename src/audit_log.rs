@@ -0,0 +1,301 @@
+//! Message-absorbing hash chains for tamper-evident, append-only logs.
+//!
+//! Every step of a plain chain computes h_{i+1} = H(h_i). A log chain instead
+//! absorbs one message block per step, h_{i+1} = H(h_i || m_i), where m_i is the
+//! Poseidon digest of the i-th log entry. The block is private witness data of its
+//! recursive layer, so a proof is small and reveals nothing about the log beyond its
+//! head, while anyone holding the entries can check that the head commits to exactly
+//! those entries, in that order.
+//!
+//! The public inputs follow `ChainPublicInputs`, with the seed as initial hash, the
+//! log head as final hash and the number of entries as steps.
+use plonky2::{
+    field::{extension::Extendable, types::Field},
+    hash::{
+        hash_types::{RichField, NUM_HASH_OUT_ELTS},
+        hashing::hash_n_to_hash_no_pad,
+        poseidon::{PoseidonHash, PoseidonPermutation},
+    },
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::CircuitConfig,
+        config::{AlgebraicHasher, GenericConfig},
+    },
+    recursion::cyclic_recursion::check_cyclic_proof_verifier_data,
+};
+
+use crate::{
+    hasher::ChainHasher, seed_from_bytes, verifier::HashChainVerifier, ChainLayer,
+    ChainPublicInputs, CyclicCircuit, CyclicProver, HashChain, HashChainError, PoseidonHasher,
+    Proof,
+};
+
+/// Number of field elements in the message block absorbed by each step.
+pub const LOG_BLOCK_WIDTH: usize = NUM_HASH_OUT_ELTS;
+
+/// The message block of a log entry: its bytes absorbed into a Poseidon hash.
+pub fn entry_block<F: RichField>(entry: &[u8]) -> [F; LOG_BLOCK_WIDTH] {
+    seed_from_bytes(entry)
+}
+
+/// One step of a log chain natively: the head after absorbing `block` into `head`.
+pub fn absorb<F: RichField>(
+    head: &[F; NUM_HASH_OUT_ELTS],
+    block: &[F; LOG_BLOCK_WIDTH],
+) -> [F; NUM_HASH_OUT_ELTS] {
+    let mut inputs = head.to_vec();
+    inputs.extend_from_slice(block);
+    hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(&inputs).elements
+}
+
+/// The head of the log chain from `seed` after absorbing `entries` in order.
+pub fn log_head<F: RichField, E: AsRef<[u8]>>(
+    seed: [F; NUM_HASH_OUT_ELTS],
+    entries: impl IntoIterator<Item = E>,
+) -> [F; NUM_HASH_OUT_ELTS] {
+    entries.into_iter().fold(seed, |head, entry| {
+        absorb(&head, &entry_block(entry.as_ref()))
+    })
+}
+
+/// Builds the cyclic circuit for log chains once, and proves and appends to any
+/// number of logs against it.
+pub struct LogChainProver<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    circuit: CyclicCircuit<F, C, D>,
+    block_target: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    LogChainProver<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Build and compile the cyclic circuit for log chains with the standard
+    /// recursion config.
+    pub fn new() -> Result<Self, HashChainError> {
+        Self::with_config(CircuitConfig::standard_recursion_config())
+    }
+
+    /// Build and compile the cyclic circuit for log chains with `config`.
+    pub fn with_config(config: CircuitConfig) -> Result<Self, HashChainError> {
+        let (circuit, block_target) =
+            <CircuitBuilder<F, D> as HashChain<F, D, C, PoseidonHasher>>::build_cyclic_circuit(
+                &config,
+                Self::build_layer,
+            )?;
+
+        Ok(Self {
+            circuit,
+            block_target,
+        })
+    }
+
+    // Build one layer of the cyclic circuit, returning it with the message block
+    // targets.
+    fn build_layer(
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Result<(ChainLayer, Vec<Target>), HashChainError> {
+        let initial_hash_target = <PoseidonHasher as ChainHasher<F, D>>::add_virtual_state(builder);
+        let condition = builder.add_virtual_bool_target_safe();
+
+        // Absorb the message block of this layer into the head of the inner proof.
+        // The base case absorbs nothing, so a log of n entries is n recursive layers.
+        let current_hash_in = <PoseidonHasher as ChainHasher<F, D>>::add_virtual_state(builder);
+        let block_target = builder.add_virtual_targets(LOG_BLOCK_WIDTH);
+        let absorbed = builder
+            .hash_n_to_hash_no_pad::<PoseidonHash>(
+                current_hash_in
+                    .iter()
+                    .chain(&block_target)
+                    .copied()
+                    .collect(),
+            )
+            .elements;
+        let latest_hash: Vec<Target> = absorbed
+            .iter()
+            .zip(&initial_hash_target)
+            .map(|(hash_out, initial)| builder.select(condition, *hash_out, *initial))
+            .collect();
+
+        let layer = ChainLayer {
+            public_inputs: ChainPublicInputs {
+                initial_hash: initial_hash_target,
+                final_hash: latest_hash,
+                steps: builder.add_virtual_target(),
+                verifier_digest: Vec::new(),
+            },
+            condition,
+            current_hash_in,
+            hashes_per_layer: 1,
+        };
+        Ok((layer, block_target))
+    }
+
+    /// Prove the log chain from `seed` over `entries`, one recursive layer per
+    /// entry.
+    pub fn prove_log<E: AsRef<[u8]>>(
+        &self,
+        seed: [F; NUM_HASH_OUT_ELTS],
+        entries: impl IntoIterator<Item = E>,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        let base =
+            <CircuitBuilder<F, D> as HashChain<F, D, C, PoseidonHasher>>::process_recursive_layer(
+                self.circuit.condition,
+                &self.circuit.inner_cyclic_proof_with_pub_inputs,
+                &self.circuit.data.common,
+                &self.circuit.data,
+                &self.circuit.verifier_data_target,
+                &seed,
+                &self.block_witness(&[F::ZERO; LOG_BLOCK_WIDTH]),
+                0,
+            )?;
+        self.absorb_entries(base, entries)
+    }
+
+    /// Append `entries` to a log chain proof of this prover. The proof is checked
+    /// first, so that a proof of another circuit fails here.
+    pub fn append<E: AsRef<[u8]>>(
+        &self,
+        proof: Proof<F, C, D>,
+        entries: impl IntoIterator<Item = E>,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        check_cyclic_proof_verifier_data(
+            &proof,
+            &self.circuit.data.verifier_only,
+            &self.circuit.data.common,
+        )?;
+        self.circuit.data.verify(proof.clone())?;
+        self.absorb_entries(proof, entries)
+    }
+
+    // Prove one recursive layer per entry, with the entry's block as its witness.
+    fn absorb_entries<E: AsRef<[u8]>>(
+        &self,
+        proof: Proof<F, C, D>,
+        entries: impl IntoIterator<Item = E>,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        let mut proof = proof;
+        for entry in entries {
+            proof = <CircuitBuilder<F, D> as HashChain<F, D, C, PoseidonHasher>>::check_cyclic_proof_layer(
+                self.circuit.condition,
+                self.circuit.inner_cyclic_proof_with_pub_inputs.clone(),
+                proof,
+                self.circuit.verifier_data_target.clone(),
+                &self.circuit.data,
+                &self.block_witness(&entry_block(entry.as_ref())),
+            )?;
+        }
+        Ok(proof)
+    }
+
+    // Witness for the message block targets.
+    fn block_witness(&self, block: &[F; LOG_BLOCK_WIDTH]) -> PartialWitness<F> {
+        let mut pw = PartialWitness::new();
+        for (target, value) in self.block_target.iter().zip(block) {
+            pw.set_target(*target, *value);
+        }
+        pw
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    CyclicProver<F, C, D> for LogChainProver<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    fn cyclic_circuit(&self) -> &CyclicCircuit<F, C, D> {
+        &self.circuit
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    HashChainVerifier<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Verify a log chain proof and check that its head commits to exactly
+    /// `entries`, in order, starting from `seed`.
+    pub fn verify_log<E: AsRef<[u8]>>(
+        &self,
+        proof: Proof<F, C, D>,
+        seed: [F; NUM_HASH_OUT_ELTS],
+        entries: impl IntoIterator<Item = E>,
+    ) -> Result<(), HashChainError> {
+        let mut head = seed;
+        let mut num_entries = 0;
+        for entry in entries {
+            head = absorb(&head, &entry_block(entry.as_ref()));
+            num_entries += 1;
+        }
+
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof)?;
+        if public_inputs.initial_hash != seed || public_inputs.final_hash != head {
+            return Err(HashChainError::LogMismatch {
+                entries: num_entries,
+            });
+        }
+        self.verify_steps::<PoseidonHasher>(proof, num_entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        audit_log::{log_head, LogChainProver},
+        seed_from_bytes, ChainPublicInputs, CyclicProver, HashChainError, PoseidonHasher,
+    };
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_log_chain() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let prover = LogChainProver::<F, C, D>::new().unwrap();
+        let verifier = prover.verifier();
+        let seed = seed_from_bytes::<F>(b"audit log");
+        let entries = [
+            "alice logged in",
+            "alice read /etc/passwd",
+            "alice logged out",
+        ];
+
+        let proof = prover.prove_log(seed, entries).unwrap();
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof).unwrap();
+        assert_eq!(public_inputs.final_hash, log_head(seed, entries));
+        assert_eq!(public_inputs.step_count(), 3);
+        assert!(verifier.verify_log(proof.clone(), seed, entries).is_ok());
+
+        // An edited, reordered or truncated log does not match the head.
+        for tampered in [
+            vec![
+                "alice logged in",
+                "alice read /etc/shadow",
+                "alice logged out",
+            ],
+            vec![
+                "alice read /etc/passwd",
+                "alice logged in",
+                "alice logged out",
+            ],
+            vec!["alice logged in", "alice read /etc/passwd"],
+        ] {
+            assert!(matches!(
+                verifier.verify_log(proof.clone(), seed, tampered),
+                Err(HashChainError::LogMismatch { .. })
+            ));
+        }
+
+        // Appending to the log extends the proof without re-proving its history.
+        let proof = prover.append(proof, ["bob logged in"]).unwrap();
+        let log = entries.iter().chain(&["bob logged in"]);
+        assert!(verifier.verify_log(proof, seed, log).is_ok());
+    }
+}
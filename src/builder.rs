@@ -0,0 +1,234 @@
+//! Builder-style entry point for hash chains.
+//!
+//! `HashChainBuilder` collects the hasher, circuit config, seed and length of a
+//! chain and compiles the cyclic circuit once, returning a `HashChainProver` and the
+//! matching `HashChainVerifier`:
+//!
+//! ```ignore
+//! let (prover, verifier) = HashChainBuilder::<F, C, D>::new()
+//!     .hasher::<Keccak256Hasher>()
+//!     .seed(seed)
+//!     .steps(16)
+//!     .build()?;
+//! let proof = prover.prove_chain()?;
+//! verifier.verify(proof)?;
+//! ```
+use std::marker::PhantomData;
+
+use plonky2::{
+    field::{extension::Extendable, types::Field},
+    hash::hash_types::RichField,
+    plonk::{
+        circuit_data::CircuitConfig,
+        config::{AlgebraicHasher, GenericConfig},
+    },
+};
+
+use crate::{
+    hasher::ChainHasher,
+    prover::HashChainProver,
+    security::SecurityPreset,
+    verifier::HashChainVerifier,
+    zk::{SeedVisibility, SEED_SALT_WIDTH},
+    CyclicProver, HashChainError, PoseidonHasher,
+};
+
+pub struct HashChainBuilder<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+    H: ChainHasher<F, D> = PoseidonHasher,
+> {
+    config: CircuitConfig,
+    zero_knowledge: Option<bool>,
+    seed_visibility: Option<SeedVisibility>,
+    seed: H::State,
+    salt: Option<[F; SEED_SALT_WIDTH]>,
+    steps: usize,
+    hashes_per_layer: usize,
+    _marker: PhantomData<(F, C)>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize> Default
+    for HashChainBuilder<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    HashChainBuilder<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// A Poseidon chain of one step from the all-zero seed, built with the standard
+    /// recursion config.
+    pub fn new() -> Self {
+        Self {
+            config: CircuitConfig::standard_recursion_config(),
+            zero_knowledge: None,
+            seed_visibility: None,
+            seed: Default::default(),
+            salt: None,
+            steps: 1,
+            hashes_per_layer: 1,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + 'static,
+        const D: usize,
+        H: ChainHasher<F, D>,
+    > HashChainBuilder<F, C, D, H>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Use `H2` as the step function. The seed is a state of the hasher, so it is
+    /// reset to the default state of `H2`; set it after picking the hasher.
+    pub fn hasher<H2: ChainHasher<F, D>>(self) -> HashChainBuilder<F, C, D, H2> {
+        HashChainBuilder {
+            config: self.config,
+            zero_knowledge: self.zero_knowledge,
+            seed_visibility: self.seed_visibility,
+            seed: H2::State::default(),
+            salt: self.salt,
+            steps: self.steps,
+            hashes_per_layer: self.hashes_per_layer,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The config of the cyclic circuit and of its recursion common data.
+    pub fn config(mut self, config: CircuitConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Use the circuit config of `preset`, trading prover time, proof size and
+    /// security. Like `config`, this replaces any config set before.
+    pub fn security_preset(self, preset: SecurityPreset) -> Self {
+        self.config(preset.circuit_config())
+    }
+
+    /// The initial hash of the chain.
+    pub fn seed(mut self, seed: H::State) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The number of hash applications proven by `HashChainProver::prove_chain`.
+    pub fn steps(mut self, steps: usize) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// The number of hashes each recursion layer applies, between 1 and
+    /// `MAX_HASHES_PER_LAYER`. Defaults to 1. Chain lengths must be multiples of it.
+    pub fn hashes_per_layer(mut self, hashes_per_layer: usize) -> Self {
+        self.hashes_per_layer = hashes_per_layer;
+        self
+    }
+
+    /// Blind the cyclic circuit. When set, this overrides the `zero_knowledge` flag
+    /// of the config passed to `config`. Unless `seed_visibility` says otherwise, a
+    /// zero-knowledge chain keeps its seed out of the public inputs.
+    pub fn zero_knowledge(mut self, zero_knowledge: bool) -> Self {
+        self.zero_knowledge = Some(zero_knowledge);
+        self
+    }
+
+    /// How the seed appears in the public inputs. Defaults to
+    /// `SeedVisibility::Hidden` for zero-knowledge chains and to
+    /// `SeedVisibility::Public` otherwise.
+    pub fn seed_visibility(mut self, seed_visibility: SeedVisibility) -> Self {
+        self.seed_visibility = Some(seed_visibility);
+        self
+    }
+
+    /// The salt of the seed commitment published with `SeedVisibility::Committed`,
+    /// or the tag published with `SeedVisibility::Tagged`. Either needs one, and
+    /// `build` fails without it.
+    pub fn salt(mut self, salt: [F; SEED_SALT_WIDTH]) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// Compile the cyclic circuit and return a prover for the configured chain
+    /// together with a verifier for its proofs.
+    pub fn build(
+        self,
+    ) -> Result<(HashChainProver<F, C, D, H>, HashChainVerifier<F, C, D>), HashChainError> {
+        let mut config = self.config;
+        if let Some(zero_knowledge) = self.zero_knowledge {
+            config.zero_knowledge = zero_knowledge;
+        }
+        let seed_visibility = self.seed_visibility.unwrap_or(if config.zero_knowledge {
+            SeedVisibility::Hidden
+        } else {
+            SeedVisibility::Public
+        });
+        let salt = match (seed_visibility, self.salt) {
+            (SeedVisibility::Committed | SeedVisibility::Tagged, None) => {
+                return Err(HashChainError::MissingSalt)
+            }
+            (_, salt) => salt.unwrap_or([F::ZERO; SEED_SALT_WIDTH]),
+        };
+        let prover = HashChainProver::<F, C, D, H>::with_layout(
+            config,
+            seed_visibility,
+            self.hashes_per_layer,
+        )?
+        .with_chain(self.seed, salt, self.steps);
+        let verifier = prover.verifier();
+        Ok((prover, verifier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        builder::HashChainBuilder, seed_from_bytes, ChainPublicInputs, CyclicProver,
+        PoseidonHasher, SeedVisibility,
+    };
+    use plonky2::plonk::{
+        circuit_data::CircuitConfig,
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+
+    #[test]
+    fn test_builder_settings_take_effect() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.num_query_rounds = 20;
+        let seed = seed_from_bytes::<F>(b"builder seed");
+
+        let (prover, verifier) = HashChainBuilder::<F, C, D>::new()
+            .config(config.clone())
+            .zero_knowledge(true)
+            .seed_visibility(SeedVisibility::Public)
+            .seed(seed)
+            .steps(3)
+            .build()
+            .unwrap();
+
+        config.zero_knowledge = true;
+        assert_eq!(prover.circuit_data().common.config, config);
+        assert_eq!(verifier.common().config, config);
+
+        let proof = prover.prove_chain().unwrap();
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof).unwrap();
+        assert_eq!(public_inputs.initial_hash, seed);
+        assert_eq!(public_inputs.step_count(), 3);
+        assert!(verifier.verify_steps::<PoseidonHasher>(proof, 3).is_ok());
+    }
+}
@@ -0,0 +1,390 @@
+//! Checkpointing for long-running chain proofs.
+//!
+//! A chain is proven in chunks of `interval` steps, and the proof after every chunk
+//! is written to a checkpoint directory. If the process dies, `resume` picks up the
+//! newest checkpoint that still verifies and proves the remaining steps on top of it
+//! with `HashChainProver::extend`.
+//!
+//! Checkpoint names carry a chain id, derived from the initial hash public inputs,
+//! next to the step count, so chains with different seeds can share a directory.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+use plonky2::{
+    field::{extension::Extendable, types::PrimeField64},
+    hash::{hash_types::RichField, hashing::hash_n_to_hash_no_pad, poseidon::PoseidonPermutation},
+    plonk::config::{AlgebraicHasher, GenericConfig},
+};
+
+use crate::{
+    hasher::ChainHasher,
+    storage::{load_proof, save_proof},
+    zk::SEED_SALT_WIDTH,
+    ChainPublicInputs, CyclicProver, HashChainError, HashChainProver, Proof,
+};
+
+const CHECKPOINT_PREFIX: &str = "checkpoint-";
+const CHECKPOINT_SUFFIX: &str = ".bin";
+
+#[derive(Debug, Clone)]
+pub struct CheckpointConfig {
+    /// Directory the checkpoints are written to.
+    pub dir: PathBuf,
+    /// Number of steps proven between two checkpoints. It is rounded up to a
+    /// multiple of the hashes per layer of the prover.
+    pub interval: usize,
+    /// Number of most recent checkpoints of a chain kept on disk. Its older ones are
+    /// removed.
+    pub keep: usize,
+}
+
+impl CheckpointConfig {
+    /// Checkpoint every `interval` steps into `dir`, keeping the two most recent
+    /// checkpoints so that one survives a crash while the next is being written.
+    pub fn new(dir: impl Into<PathBuf>, interval: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            interval,
+            keep: 2,
+        }
+    }
+}
+
+impl<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + 'static,
+        const D: usize,
+        H: ChainHasher<F, D>,
+    > HashChainProver<F, C, D, H>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Prove a chain of `steps` hashes starting from `seed`, writing a checkpoint
+    /// every `config.interval` steps. A committed seed is committed to with `salt`,
+    /// as by `prove_with_salt`.
+    pub fn prove_with_checkpoints(
+        &self,
+        seed: H::State,
+        salt: [F; SEED_SALT_WIDTH],
+        steps: usize,
+        config: &CheckpointConfig,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        // Reject a length that is not in whole layers before writing anything.
+        self.layers(steps)?;
+        fs::create_dir_all(&config.dir)?;
+        // The first checkpoint already covers a chunk of steps: a chain of none would
+        // carry the seed itself as its final hash.
+        let chunk = self.checkpoint_interval(config).min(steps);
+        let proof = self.prove_with_salt(seed, salt, chunk)?;
+        self.write_checkpoint(&proof, config)?;
+        info!("Checkpointed {} of {} steps", chunk, steps);
+        self.extend_with_checkpoints(proof, steps, config)
+    }
+
+    /// Continue proving a chain of `steps` hashes from `seed` and `salt` from the
+    /// newest valid checkpoint in `config.dir`. Checkpoints that fail to load, fail
+    /// to verify, belong to a chain with a different seed or cover more than `steps`
+    /// steps are skipped. Without any valid checkpoint the chain is proven from the
+    /// start. A hidden seed leaves nothing to compare or to derive a chain id from,
+    /// so `config.dir` must then only hold checkpoints of this chain.
+    pub fn resume(
+        &self,
+        seed: H::State,
+        salt: [F; SEED_SALT_WIDTH],
+        steps: usize,
+        config: &CheckpointConfig,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        self.layers(steps)?;
+        let initial_hash = self.initial_hash_inputs(&seed, &salt);
+        let verifier = self.verifier();
+        for path in chain_checkpoints(&config.dir, &chain_id(&initial_hash))? {
+            let (proof, public_inputs) = match self.load_checkpoint(&path) {
+                Ok(checkpoint) => checkpoint,
+                Err(err) => {
+                    info!("Skipping checkpoint {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            if public_inputs.initial_hash != initial_hash {
+                info!("Skipping checkpoint {}: different seed", path.display());
+                continue;
+            }
+            if public_inputs.step_count() > steps as u64 {
+                info!(
+                    "Skipping checkpoint {}: covers {} of {} steps",
+                    path.display(),
+                    public_inputs.step_count(),
+                    steps
+                );
+                continue;
+            }
+            if let Err(err) = verifier.verify(proof.clone()) {
+                info!("Skipping checkpoint {}: {}", path.display(), err);
+                continue;
+            }
+            info!("Resuming from checkpoint {}", path.display());
+            return self.extend_with_checkpoints(proof, steps, config);
+        }
+        info!("No valid checkpoint in {}", config.dir.display());
+        self.prove_with_checkpoints(seed, salt, steps, config)
+    }
+
+    // Extend a checkpointed proof to `steps` hashes in chunks of `config.interval`,
+    // writing a checkpoint after every chunk.
+    fn extend_with_checkpoints(
+        &self,
+        proof: Proof<F, C, D>,
+        steps: usize,
+        config: &CheckpointConfig,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        let mut done = ChainPublicInputs::from_proof::<C, D, H>(&proof)?.step_count() as usize;
+        if done > steps {
+            return Err(anyhow::Error::msg(format!(
+                "Checkpoint already covers {} steps, more than the {} requested.",
+                done, steps
+            ))
+            .into());
+        }
+        let interval = self.checkpoint_interval(config);
+        let mut proof = proof;
+        while done < steps {
+            let chunk = interval.min(steps - done);
+            proof = self.extend(proof, chunk)?;
+            done += chunk;
+            self.write_checkpoint(&proof, config)?;
+            info!("Checkpointed {} of {} steps", done, steps);
+        }
+        Ok(proof)
+    }
+
+    // Write the proof under a temporary name and rename it into place, so that a
+    // crash mid-write never leaves a truncated file under a checkpoint name.
+    // Checkpoints of the same chain beyond the `keep` most recent are removed
+    // afterwards. Files that do not load, or hold another chain, are left alone, so
+    // that they do not crowd out this chain's checkpoints.
+    fn write_checkpoint(
+        &self,
+        proof: &Proof<F, C, D>,
+        config: &CheckpointConfig,
+    ) -> Result<(), HashChainError> {
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, H>(proof)?;
+        let chain_id = chain_id(&public_inputs.initial_hash);
+        let path = checkpoint_path(&config.dir, &chain_id, public_inputs.step_count());
+        let tmp_path = path.with_extension("tmp");
+        save_proof(proof, &tmp_path)?;
+        fs::rename(&tmp_path, &path)?;
+
+        // The checkpoint just written is always kept.
+        let mut kept = 1;
+        for other in chain_checkpoints(&config.dir, &chain_id)? {
+            if other == path {
+                continue;
+            }
+            let same_chain = self.load_checkpoint(&other).is_ok_and(|(_, other_inputs)| {
+                other_inputs.initial_hash == public_inputs.initial_hash
+            });
+            if !same_chain {
+                continue;
+            }
+            if kept < config.keep.max(1) {
+                kept += 1;
+            } else {
+                fs::remove_file(other)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Load the checkpoint at `path` along with its chain inputs.
+    fn load_checkpoint(
+        &self,
+        path: &Path,
+    ) -> Result<(Proof<F, C, D>, ChainPublicInputs<F>), HashChainError> {
+        let proof = load_proof::<F, C, D>(path, &self.circuit_data().common)?;
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, H>(&proof)?;
+        Ok((proof, public_inputs))
+    }
+
+    // The number of steps between two checkpoints, in whole layers.
+    fn checkpoint_interval(&self, config: &CheckpointConfig) -> usize {
+        config
+            .interval
+            .max(1)
+            .next_multiple_of(self.hashes_per_layer())
+    }
+}
+
+// The id of the chain with the initial hash public inputs `initial_hash`: the first
+// element of their Poseidon hash, in hex.
+fn chain_id<F: RichField>(initial_hash: &[F]) -> String {
+    let digest = hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(initial_hash);
+    format!("{:016x}", digest.elements[0].to_canonical_u64())
+}
+
+fn checkpoint_path(dir: &Path, chain_id: &str, counter: u64) -> PathBuf {
+    dir.join(format!(
+        "{}{}-{:020}{}",
+        CHECKPOINT_PREFIX, chain_id, counter, CHECKPOINT_SUFFIX
+    ))
+}
+
+// The chain id and step count in the name of a checkpoint.
+fn parse_checkpoint_path(path: &Path) -> Option<(String, u64)> {
+    let (chain_id, counter) = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix(CHECKPOINT_PREFIX)?
+        .strip_suffix(CHECKPOINT_SUFFIX)?
+        .split_once('-')?;
+    Some((chain_id.to_string(), counter.parse().ok()?))
+}
+
+/// List the checkpoints of every chain in `dir`, newest first.
+pub fn list_checkpoints(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, HashChainError> {
+    let dir = dir.as_ref();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut checkpoints = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some((_, counter)) = parse_checkpoint_path(&path) {
+            checkpoints.push((counter, path));
+        }
+    }
+    checkpoints.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(checkpoints.into_iter().map(|(_, path)| path).collect())
+}
+
+// List the checkpoints of the chain `chain_id` in `dir`, newest first.
+fn chain_checkpoints(dir: &Path, chain_id: &str) -> Result<Vec<PathBuf>, HashChainError> {
+    Ok(list_checkpoints(dir)?
+        .into_iter()
+        .filter(|path| parse_checkpoint_path(path).is_some_and(|(id, _)| id == chain_id))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        checkpoint::{chain_id, checkpoint_path, list_checkpoints, CheckpointConfig},
+        seed_from_bytes,
+        storage::load_proof,
+        zk::SEED_SALT_WIDTH,
+        ChainPublicInputs, CyclicProver, HashChainProver, PoseidonHasher,
+    };
+    use plonky2::{
+        field::types::Field,
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+    use std::fs;
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let dir =
+            std::env::temp_dir().join(format!("hash-chain-checkpoint-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = CheckpointConfig::new(&dir, 1);
+
+        let prover = HashChainProver::<F, C, D>::new().unwrap();
+        let seed = seed_from_bytes::<F>(b"long running chain");
+        let salt = [F::ZERO; SEED_SALT_WIDTH];
+        let id = chain_id(&seed);
+
+        // A run that was killed after 2 of its steps, leaving a corrupt file behind.
+        prover
+            .prove_with_checkpoints(seed, salt, 2, &config)
+            .unwrap();
+        assert_eq!(list_checkpoints(&dir).unwrap().len(), 2);
+        fs::write(checkpoint_path(&dir, &id, 1_000), b"not a proof").unwrap();
+
+        // The corrupt file is skipped and the chain continues from the last valid
+        // checkpoint.
+        let proof = prover.resume(seed, salt, 4, &config).unwrap();
+        let expected = prover.prove(seed, 4).unwrap();
+        assert_eq!(proof.public_inputs, expected.public_inputs);
+        assert!(prover.verifier().verify(proof).is_ok());
+
+        // A checkpoint that already covers more steps than requested is skipped for
+        // an older one.
+        fs::remove_file(checkpoint_path(&dir, &id, 1_000)).unwrap();
+        let proof = prover.resume(seed, salt, 3, &config).unwrap();
+        assert_eq!(
+            proof.public_inputs,
+            prover.prove(seed, 3).unwrap().public_inputs
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shared_checkpoint_dir() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let dir = std::env::temp_dir().join(format!("hash-chain-shared-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut config = CheckpointConfig::new(&dir, 1);
+        config.keep = 1;
+
+        let prover = HashChainProver::<F, C, D>::new().unwrap();
+        let seed = seed_from_bytes::<F>(b"shared dir chain");
+        let other_seed = seed_from_bytes::<F>(b"other chain");
+        let salt = [F::ZERO; SEED_SALT_WIDTH];
+        let (id, other_id) = (chain_id(&seed), chain_id(&other_seed));
+        let sorted = |mut paths: Vec<_>| {
+            paths.sort();
+            paths
+        };
+
+        // Another chain reaches the same counter in the same directory, next to a
+        // corrupt file numbered above both.
+        prover
+            .prove_with_checkpoints(other_seed, salt, 3, &config)
+            .unwrap();
+        fs::write(checkpoint_path(&dir, &id, 1_000), b"not a proof").unwrap();
+        prover
+            .prove_with_checkpoints(seed, salt, 3, &config)
+            .unwrap();
+
+        // Neither chain overwrites nor prunes the other's checkpoints.
+        let expected = vec![
+            checkpoint_path(&dir, &id, 1_000),
+            checkpoint_path(&dir, &id, 3),
+            checkpoint_path(&dir, &other_id, 3),
+        ];
+        assert_eq!(sorted(list_checkpoints(&dir).unwrap()), sorted(expected));
+        let other_checkpoint = load_proof::<F, C, D>(
+            checkpoint_path(&dir, &other_id, 3),
+            &prover.circuit_data().common,
+        )
+        .unwrap();
+        let other_inputs =
+            ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&other_checkpoint).unwrap();
+        assert_eq!(other_inputs.initial_hash, other_seed);
+
+        // The fresh checkpoint survives, so the chain resumes from it.
+        let proof = prover.resume(seed, salt, 4, &config).unwrap();
+        assert_eq!(
+            proof.public_inputs,
+            prover.prove(seed, 4).unwrap().public_inputs
+        );
+        let expected = vec![
+            checkpoint_path(&dir, &id, 1_000),
+            checkpoint_path(&dir, &id, 4),
+            checkpoint_path(&dir, &other_id, 3),
+        ];
+        assert_eq!(sorted(list_checkpoints(&dir).unwrap()), sorted(expected));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,49 @@
+//! The step function of a hash chain.
+//!
+//! A `ChainHasher` bundles everything the recursive machinery in `HashChain` needs
+//! to know about a hash: the in-circuit gadget, its native counterpart, how wide the
+//! chain state is once it is laid out as public inputs, and how to move between the
+//! native state and that encoding. The state occupies `ChainHasher::STATE_WIDTH`
+//! elements of each hash in the public inputs, whose layout is described by
+//! `ChainPublicInputs`.
+use std::fmt::Debug;
+
+use plonky2::{
+    field::extension::Extendable, hash::hash_types::RichField, iop::target::Target,
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::HashChainError;
+
+pub trait ChainHasher<F: RichField + Extendable<D>, const D: usize> {
+    /// Native chain state, e.g. four field elements for Poseidon or a 32-byte digest.
+    /// The default value is the seed used when the caller does not supply one.
+    type State: Clone + Debug + Default + PartialEq + Send + Sync;
+
+    /// Number of field elements the state occupies in the public inputs.
+    const STATE_WIDTH: usize;
+
+    /// Allocate `STATE_WIDTH` targets holding a chain state.
+    fn add_virtual_state(builder: &mut CircuitBuilder<F, D>) -> Vec<Target>;
+
+    /// Apply the hash to a chain state in-circuit.
+    fn hash_circuit(builder: &mut CircuitBuilder<F, D>, state: &[Target]) -> Vec<Target>;
+
+    /// Apply the hash to a chain state natively.
+    fn hash(state: &Self::State) -> Self::State;
+
+    /// Encode a chain state as `STATE_WIDTH` public inputs.
+    fn state_to_public_inputs(state: &Self::State) -> Vec<F>;
+
+    /// Decode a chain state from `STATE_WIDTH` public inputs.
+    fn state_from_public_inputs(inputs: &[F]) -> Result<Self::State, HashChainError>;
+
+    /// Iterate the hash n number of times natively.
+    fn iterate(initial_state: Self::State, n: usize) -> Self::State {
+        let mut current = initial_state;
+        for _ in 0..n {
+            current = Self::hash(&current);
+        }
+        current
+    }
+}
@@ -0,0 +1,200 @@
+//! Keccak-256 hash chain built on the `plonky2_crypto` Keccak gadget.
+//!
+//! The chain state is a 32-byte Keccak-256 digest. Inside the circuit it is carried
+//! as eight little-endian `u32` limbs, which is the byte order `plonky2_crypto` uses
+//! for its hash inputs and outputs. The state occupies public inputs `[0..8]` and
+//! `[8..16]` and the counter sits at `[16]`.
+use plonky2::{
+    field::extension::Extendable, hash::hash_types::RichField, iop::target::Target,
+    plonk::circuit_builder::CircuitBuilder,
+};
+use plonky2_crypto::{
+    hash::{keccak256::CircuitBuilderHashKeccak, CircuitBuilderHash},
+    u32::arithmetic_u32::{CircuitBuilderU32, U32Target},
+};
+use sha3::{Digest, Keccak256};
+
+use crate::{hasher::ChainHasher, HashChainError, KECCAK256_R};
+
+/// Number of `u32` limbs in a Keccak-256 digest.
+pub const KECCAK256_DIGEST_LIMBS: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Keccak256Hasher;
+
+impl<F: RichField + Extendable<D>, const D: usize> ChainHasher<F, D> for Keccak256Hasher {
+    type State = [u8; 32];
+
+    const STATE_WIDTH: usize = KECCAK256_DIGEST_LIMBS;
+
+    // Range check the limbs, so that a seed cannot carry values that are not `u32`s
+    // into the public inputs.
+    fn add_virtual_state(builder: &mut CircuitBuilder<F, D>) -> Vec<Target> {
+        let limbs = builder.add_virtual_u32_targets(KECCAK256_DIGEST_LIMBS);
+        builder.range_check_u32(limbs.clone());
+        limb_targets(&limbs)
+    }
+
+    // Apply Keccak-256 to a 32-byte state given as little-endian `u32` limbs. The
+    // input block is constrained to the state followed by its pad10*1 padding, so
+    // the prover has no freedom over anything but the (already constrained) state.
+    fn hash_circuit(builder: &mut CircuitBuilder<F, D>, state: &[Target]) -> Vec<Target> {
+        let input = builder.add_virtual_hash_input_target(1, KECCAK256_R);
+        let last_limb = KECCAK256_R / 32 - 1;
+        for (i, limb) in input.input.limbs.iter().enumerate() {
+            match state.get(i) {
+                Some(state_limb) => builder.connect(limb.0, *state_limb),
+                None => {
+                    let padding = match i {
+                        KECCAK256_DIGEST_LIMBS => 0x0000_0001,
+                        i if i == last_limb => 0x8000_0000,
+                        _ => 0,
+                    };
+                    let padding = builder.constant_u32(padding);
+                    builder.connect_u32(*limb, padding);
+                }
+            }
+        }
+        let no_more_blocks = builder._false();
+        for block in &input.blocks {
+            builder.connect(block.target, no_more_blocks.target);
+        }
+        limb_targets(&builder.hash_keccak256(&input).limbs)
+    }
+
+    fn hash(state: &Self::State) -> Self::State {
+        Keccak256::digest(state).into()
+    }
+
+    fn state_to_public_inputs(state: &Self::State) -> Vec<F> {
+        keccak_digest_to_limbs::<F>(state).to_vec()
+    }
+
+    fn state_from_public_inputs(inputs: &[F]) -> Result<Self::State, HashChainError> {
+        let limbs: [F; KECCAK256_DIGEST_LIMBS] = inputs.try_into()?;
+        keccak_limbs_to_digest(&limbs)
+    }
+}
+
+fn limb_targets(limbs: &[U32Target]) -> Vec<Target> {
+    limbs.iter().map(|limb| limb.0).collect()
+}
+
+/// Encode a Keccak-256 digest as the eight little-endian `u32` limbs used for the
+/// chain's public inputs.
+pub fn keccak_digest_to_limbs<F: RichField>(digest: &[u8; 32]) -> [F; KECCAK256_DIGEST_LIMBS] {
+    std::array::from_fn(|i| {
+        let word = u32::from_le_bytes(digest[4 * i..4 * i + 4].try_into().unwrap());
+        F::from_canonical_u32(word)
+    })
+}
+
+/// Decode eight little-endian `u32` limbs from the chain's public inputs back into a
+/// Keccak-256 digest, failing on limbs that do not fit in a `u32`.
+pub fn keccak_limbs_to_digest<F: RichField>(limbs: &[F]) -> Result<[u8; 32], HashChainError> {
+    let mut digest = [0u8; 32];
+    for (bytes, limb) in digest.chunks_exact_mut(4).zip(limbs) {
+        let value = limb.to_canonical_u64();
+        let limb = u32::try_from(value).map_err(|_| HashChainError::LimbOutOfRange { value })?;
+        bytes.copy_from_slice(&limb.to_le_bytes());
+    }
+    Ok(digest)
+}
+
+/// Iterate Keccak-256 n number of times, the native counterpart of the Keccak chain.
+pub fn iterate_keccak(initial_state: [u8; 32], n: usize) -> [u8; 32] {
+    let mut current = initial_state;
+    for _ in 0..n {
+        current = Keccak256::digest(current).into();
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        keccak::{iterate_keccak, keccak_limbs_to_digest, Keccak256Hasher},
+        HashChain, HashChainError,
+    };
+    use plonky2::{
+        field::{
+            goldilocks_field::GoldilocksField,
+            types::{Field, PrimeField64},
+        },
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+    use sha3::{Digest, Keccak256};
+
+    #[test]
+    fn test_keccak_hash_chain() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let seed: [u8; 32] = Keccak256::digest(b"keccak hash chain seed").into();
+        let config = CircuitConfig::standard_recursion_config();
+        let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
+        let (proof, circuit_map) =
+            <CircuitBuilder<GoldilocksField, D> as HashChain<
+                GoldilocksField,
+                D,
+                C,
+                Keccak256Hasher,
+            >>::build_hash_chain_circuit_with_seed(&mut circuit, seed, 2)
+            .unwrap();
+
+        assert_eq!(
+            keccak_limbs_to_digest(&proof.public_inputs[0..8]).unwrap(),
+            seed
+        );
+
+        // Check the circuit output against the sha3 crate directly.
+        let mut expected = seed;
+        for _ in 0..proof.public_inputs[16].to_canonical_u64() {
+            expected = Keccak256::digest(expected).into();
+        }
+        assert_eq!(
+            keccak_limbs_to_digest(&proof.public_inputs[8..16]).unwrap(),
+            expected
+        );
+
+        let result = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+            Keccak256Hasher,
+        >>::audit_verify(proof, &circuit_map);
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn test_iterate_keccak() {
+        assert_eq!(
+            hex::encode(iterate_keccak([0u8; 32], 1)),
+            "290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+        );
+        assert_eq!(
+            iterate_keccak(iterate_keccak([7u8; 32], 3), 2),
+            iterate_keccak([7u8; 32], 5)
+        );
+    }
+
+    #[test]
+    fn test_limb_out_of_range() {
+        let mut limbs = [GoldilocksField::ZERO; 8];
+        limbs[3] = GoldilocksField::from_canonical_u64(1 << 32);
+        assert!(matches!(
+            keccak_limbs_to_digest(&limbs),
+            Err(HashChainError::LimbOutOfRange { value }) if value == 1 << 32
+        ));
+
+        limbs[3] = GoldilocksField::from_canonical_u32(u32::MAX);
+        let digest = keccak_limbs_to_digest(&limbs).unwrap();
+        assert_eq!(digest[12..16], [0xff; 4]);
+    }
+}
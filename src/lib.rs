@@ -1,12 +1,7 @@
 use log::info;
 use plonky2::{
     field::extension::Extendable,
-    gates::noop::NoopGate,
-    hash::{
-        hash_types::{HashOutTarget, RichField},
-        hashing::hash_n_to_hash_no_pad,
-        poseidon::{PoseidonHash, PoseidonPermutation},
-    },
+    hash::{hash_types::RichField, hashing::hash_n_to_hash_no_pad, poseidon::PoseidonPermutation},
     iop::{
         target::{BoolTarget, Target},
         witness::{PartialWitness, WitnessWrite},
@@ -15,16 +10,55 @@ use plonky2::{
         circuit_builder::CircuitBuilder,
         circuit_data::{CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget},
         config::{AlgebraicHasher, GenericConfig},
-        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+        proof::{
+            CompressedProofWithPublicInputs, ProofWithPublicInputs, ProofWithPublicInputsTarget,
+        },
     },
     recursion::{
         cyclic_recursion::check_cyclic_proof_verifier_data, dummy_circuit::cyclic_base_proof,
     },
-    util::serialization::DefaultGateSerializer,
 };
 use std::array::TryFromSliceError;
 pub const KECCAK256_R: usize = 1088;
 
+pub mod audit_log;
+pub mod builder;
+pub mod checkpoint;
+pub mod hasher;
+pub mod keccak;
+pub mod otp;
+pub mod poh;
+pub mod poseidon;
+pub mod prover;
+pub mod public_inputs;
+pub mod security;
+pub mod segment;
+pub mod sha256;
+pub mod storage;
+pub mod telemetry;
+pub mod vdf;
+pub mod verifier;
+pub mod wrap;
+pub mod zk;
+pub use audit_log::{log_head, LogChainProver};
+pub use builder::HashChainBuilder;
+pub use checkpoint::CheckpointConfig;
+pub use hasher::ChainHasher;
+pub use keccak::{iterate_keccak, Keccak256Hasher};
+pub use otp::{one_time_password, OtpProver, OtpVerifier};
+pub use poh::{event_digest, PohEvent, PohProver};
+pub use poseidon::PoseidonHasher;
+pub use prover::{HashChainProver, MAX_HASHES_PER_LAYER};
+pub use public_inputs::ChainPublicInputs;
+pub use security::{security_report, SecurityPreset, SecurityReport};
+pub use segment::SegmentedProver;
+pub use sha256::{iterate_sha256, Sha256Hasher};
+pub use telemetry::ChainTelemetry;
+pub use vdf::{vdf_eval, vdf_verify, VdfEvaluation};
+pub use verifier::HashChainVerifier;
+pub use wrap::ShrinkWrapper;
+pub use zk::{seed_commitment, SeedVisibility};
+
 use anyhow::Error as AnyhowError;
 use thiserror::Error;
 
@@ -34,6 +68,38 @@ pub enum HashChainError {
     AnyhowError(#[from] AnyhowError),
     #[error("Failed to convert slice: {0}")]
     SliceConversionError(#[from] TryFromSliceError),
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Failed to (de)serialize {0}")]
+    SerializationError(&'static str),
+    #[error("Final hash does not match {counter} native hash applications to the initial hash")]
+    ChainMismatch { counter: u64 },
+    #[error("Proof covers {actual} steps, expected {expected}")]
+    StepCountMismatch { expected: u64, actual: u64 },
+    #[error("Expected at least {expected} public inputs, got {actual}")]
+    PublicInputsLength { expected: usize, actual: usize },
+    #[error("Public input {value} does not fit in a u32 limb")]
+    LimbOutOfRange { value: u64 },
+    #[error("Final hash does not match the anchor")]
+    AnchorMismatch,
+    #[error("Initial hash does not match the presented value")]
+    PresentedValueMismatch,
+    #[error("Initial hash does not match the enrollment")]
+    EnrollmentMismatch,
+    #[error("Final hash does not commit to the given {entries} log entries")]
+    LogMismatch { entries: u64 },
+    #[error("Event commitment does not match the given {events} events")]
+    EventMismatch { events: usize },
+    #[error("Initial hash is not the VDF seed of the challenge")]
+    ChallengeMismatch,
+    #[error("Final hash does not match the claimed VDF output")]
+    VdfOutputMismatch,
+    #[error("Recursion common data did not reach a fixed point within {iterations} builds")]
+    RecursionSizing { iterations: usize },
+    #[error("A chain with a private seed must cover at least one step")]
+    EmptyPrivateChain,
+    #[error("A chain with a committed or tagged seed needs an explicit salt")]
+    MissingSalt,
 }
 
 // Result type for operations that produce a target proof with public inputs
@@ -44,6 +110,9 @@ type ProofTargetResult<const D: usize> = Result<ProofWithPublicInputsTarget<D>,
 // easier usage within functions and trait methods across different configurations.
 type Proof<F, C, const D: usize> = ProofWithPublicInputs<F, C, D>;
 
+// Compressed form of a proof, in which the FRI query paths are deduplicated.
+type CompressedProof<F, C, const D: usize> = CompressedProofWithPublicInputs<F, C, D>;
+
 // Alias for circuit data that simplifies references to circuit structures within various methods.
 // It describes the circuit to be proven and can be used during verification.
 type CircuitMap<F, C, const D: usize> = CircuitData<F, C, D>;
@@ -58,18 +127,75 @@ type ProofAndCircuit<F, C, const D: usize> = (Proof<F, C, D>, CircuitMap<F, C, D
 // and circuit data or an error specific to hash chain processing.
 type ProofAndCircuitResult<F, C, const D: usize> = Result<ProofAndCircuit<F, C, D>, HashChainError>;
 
+/// Number of builds `HashChain::common_data_for_recursion` tries in search of a fixed
+/// point before giving up.
+pub const MAX_SIZING_ITERATIONS: usize = 8;
+
+/// One layer of a cyclic chain circuit, as built by the closure passed to
+/// `HashChain::build_cyclic_circuit`.
+pub struct ChainLayer {
+    /// The chain inputs of the layer. The verifier digest is added by the builder.
+    pub public_inputs: ChainPublicInputs<Target>,
+    /// True in every layer but the base case.
+    pub condition: BoolTarget,
+    /// The state fed into the hashes of the layer, connected to the latest hash of the
+    /// inner proof.
+    pub current_hash_in: Vec<Target>,
+    /// The number of hashes the layer applies, by which its counter advances.
+    pub hashes_per_layer: usize,
+}
+
+/// A compiled cyclic chain circuit, with the targets every layer proof sets.
+pub struct CyclicCircuit<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    pub data: CircuitMap<F, C, D>,
+    pub condition: BoolTarget,
+    pub inner_cyclic_proof_with_pub_inputs: ProofWithPublicInputsTarget<D>,
+    pub verifier_data_target: VerifierCircuitTarget,
+}
+
+/// A prover of the layers of a `CyclicCircuit`, with the accessors every such
+/// prover shares.
+pub trait CyclicProver<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    const D: usize,
+> where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// The cyclic circuit whose layers this prover proves.
+    fn cyclic_circuit(&self) -> &CyclicCircuit<F, C, D>;
+
+    /// The compiled cyclic circuit that every proof of this prover is a proof of.
+    fn circuit_data(&self) -> &CircuitMap<F, C, D> {
+        &self.cyclic_circuit().data
+    }
+
+    /// A standalone verifier for proofs of this prover.
+    fn verifier(&self) -> HashChainVerifier<F, C, D> {
+        HashChainVerifier::from_circuit_data(self.circuit_data())
+    }
+}
+
+/// The recursive machinery of a hash chain. `HashChainBuilder` is the entry point
+/// for building and proving chains; this trait exposes the individual steps.
 #[allow(clippy::too_many_arguments)]
-pub trait HashChain<F: RichField + Extendable<D>, const D: usize, C: GenericConfig<D, F = F>> {
+pub trait HashChain<
+    F: RichField + Extendable<D>,
+    const D: usize,
+    C: GenericConfig<D, F = F>,
+    H: ChainHasher<F, D> = PoseidonHasher,
+>
+{
     fn build_hash_chain_circuit(&mut self, steps: usize) -> ProofAndCircuitResult<F, C, D>;
+    fn build_hash_chain_circuit_with_seed(
+        &mut self,
+        seed: H::State,
+        steps: usize,
+    ) -> ProofAndCircuitResult<F, C, D>;
     fn setup_recursive_layers(
-        &self,
         builder: &mut CircuitBuilder<F, D>,
         common_data: CommonData<F, D>,
-        initial_hash_target: HashOutTarget,
-        condition: BoolTarget,
-        current_hash_in: HashOutTarget,
-        one: Target,
-        counter: Target,
+        layer: &ChainLayer,
     ) -> ProofTargetResult<D>;
 
     fn verify(
@@ -77,28 +203,68 @@ pub trait HashChain<F: RichField + Extendable<D>, const D: usize, C: GenericConf
         cyclic_circuit_data: &CircuitMap<F, C, D>,
     ) -> Result<(), HashChainError>;
 
+    fn verify_steps(
+        proof: Proof<F, C, D>,
+        cyclic_circuit_data: &CircuitMap<F, C, D>,
+        steps: u64,
+    ) -> Result<(), HashChainError>;
+
+    fn audit_verify(
+        proof: Proof<F, C, D>,
+        cyclic_circuit_data: &CircuitMap<F, C, D>,
+    ) -> Result<(), HashChainError>;
+
+    fn compress(
+        proof: Proof<F, C, D>,
+        cyclic_circuit_data: &CircuitMap<F, C, D>,
+    ) -> Result<CompressedProof<F, C, D>, HashChainError>;
+
+    fn verify_compressed(
+        compressed_proof: CompressedProof<F, C, D>,
+        cyclic_circuit_data: &CircuitMap<F, C, D>,
+    ) -> Result<(), HashChainError>;
+
     fn check_cyclic_proof_layer(
         condition: BoolTarget,
         inner_cyclic_proof_with_pub_inputs: ProofWithPublicInputsTarget<D>,
         proof: Proof<F, C, D>,
         verifier_data_target: VerifierCircuitTarget,
         cyclic_circuit_data: &CircuitMap<F, C, D>,
+        private_witness: &PartialWitness<F>,
     ) -> Result<Proof<F, C, D>, HashChainError>;
 
-    fn common_data_for_recursion() -> CommonData<F, D>;
+    fn common_data_for_recursion<T, L>(
+        config: &CircuitConfig,
+        build_layer: &L,
+    ) -> Result<CommonData<F, D>, HashChainError>
+    where
+        L: Fn(&mut CircuitBuilder<F, D>) -> Result<(ChainLayer, T), HashChainError>;
+
+    fn build_cyclic_circuit<T, L>(
+        config: &CircuitConfig,
+        build_layer: L,
+    ) -> Result<(CyclicCircuit<F, C, D>, T), HashChainError>
+    where
+        L: Fn(&mut CircuitBuilder<F, D>) -> Result<(ChainLayer, T), HashChainError>;
 
     fn process_recursive_layer(
         condition: BoolTarget,
-        inner_cyclic_proof_with_pub_inputs: ProofWithPublicInputsTarget<D>,
-        common_data: CommonData<F, D>,
-        cyclic_circuit_data: CircuitMap<F, C, D>,
-        verifier_data_target: VerifierCircuitTarget,
-        steps: usize,
-    ) -> ProofAndCircuitResult<F, C, D>;
+        inner_cyclic_proof_with_pub_inputs: &ProofWithPublicInputsTarget<D>,
+        common_data: &CommonData<F, D>,
+        cyclic_circuit_data: &CircuitMap<F, C, D>,
+        verifier_data_target: &VerifierCircuitTarget,
+        initial_hash: &[F],
+        private_witness: &PartialWitness<F>,
+        layers: usize,
+    ) -> Result<Proof<F, C, D>, HashChainError>;
 }
 
-impl<F: RichField + Extendable<D>, const D: usize, C: GenericConfig<D, F = F> + 'static>
-    HashChain<F, D, C> for CircuitBuilder<F, D>
+impl<
+        F: RichField + Extendable<D>,
+        const D: usize,
+        C: GenericConfig<D, F = F> + 'static,
+        H: ChainHasher<F, D>,
+    > HashChain<F, D, C, H> for CircuitBuilder<F, D>
 where
     <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
 {
@@ -186,161 +352,141 @@ where
         &mut self,
         steps: usize,
     ) -> Result<(ProofWithPublicInputs<F, C, D>, CircuitData<F, C, D>), HashChainError> {
-        let config = CircuitConfig::standard_recursion_config();
-
-        // Setup the builder for the cyclic circuit. We will proceed to add the necessary
-        // gates into this first builder, then proceed to construct additional builders
-        // and configs for each layer of recursion.
-        let mut builder = CircuitBuilder::<F, D>::new(config);
-
-        // Set a counter to be incremented and track recursion depth
-        let one = builder.one();
-
-        // Setup the initial hash target gate and register the initial hash as a public input.
-        let initial_hash_target: HashOutTarget = builder.add_virtual_hash();
-        builder.register_public_inputs(&initial_hash_target.elements);
-
-        // Insert an updateable hash gate into the circuit, so that we can
-        // update it as we recurse.
-        let current_hash_in: HashOutTarget = builder.add_virtual_hash();
-        let current_hash_out: HashOutTarget =
-            builder.hash_n_to_hash_no_pad::<PoseidonHash>(current_hash_in.elements.to_vec());
-
-        builder.register_public_inputs(&current_hash_out.elements);
-        let counter = builder.add_virtual_public_input();
-
-        // Get the `CircuitCommonData` for this circuit, which defines the configuration
-        // and partial witnesses for the recursion layers.
-        let mut common_data =
-            <CircuitBuilder<F, D> as HashChain<F, D, C>>::common_data_for_recursion();
-
-        // Define the verifier data target for the circuit.
-        let verifier_data_target = builder.add_verifier_data_public_inputs();
-        common_data.num_public_inputs = builder.num_public_inputs();
-        let gate_serializer = DefaultGateSerializer;
-        let common_data_bytes = common_data
-            .to_bytes(&gate_serializer)
-            .map_err(|_| anyhow::Error::msg("CommonCircuitData serialization failed."))?;
-        info!(
-            "Common circuit data length: {} bytes",
-            common_data_bytes.len()
-        );
-
-        info!(
-            "Initial {} degree {} = 2^{}",
-            "proof",
-            common_data.degree(),
-            common_data.degree_bits()
-        );
-
-        // Set a condition flag to determine if we are in the base case or not.
-        let condition = builder.add_virtual_bool_target_safe();
-
-        let inner_cyclic_proof_with_pub_inputs =
-            <CircuitBuilder<F, D> as HashChain<F, D, C>>::setup_recursive_layers(
-                self,
-                &mut builder,
-                common_data.clone(),
-                initial_hash_target,
-                condition,
-                current_hash_in,
-                one,
-                counter,
-            )?;
-
-        info!(
-            "Number of gates in circuit: {}",
-            builder.num_gates() * steps
-        );
-        // We now have all of the appropriate layers setup, so
-        // now lets compile the circuit.
-        let cyclic_circuit_data = builder.build::<C>();
-
-        // Enter recursive loop
-        Self::process_recursive_layer(
-            condition,
-            inner_cyclic_proof_with_pub_inputs,
-            common_data,
-            cyclic_circuit_data,
-            verifier_data_target,
+        <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::build_hash_chain_circuit_with_seed(
+            self,
+            H::State::default(),
             steps,
         )
     }
 
+    // Build and prove a hash chain that starts from a caller-supplied seed rather than
+    // the default (all-zero) state. The seed is carried by the base proof as the
+    // initial hash public inputs and is connected to the initial hash target in every
+    // subsequent layer, so the final proof attests to H^n(seed). Use `seed_from_bytes`
+    // to derive a Poseidon seed from arbitrary bytes.
+    fn build_hash_chain_circuit_with_seed(
+        &mut self,
+        seed: H::State,
+        steps: usize,
+    ) -> Result<(ProofWithPublicInputs<F, C, D>, CircuitData<F, C, D>), HashChainError> {
+        // The circuit only needs building once; `HashChainProver` keeps it around
+        // for callers that prove more than one chain.
+        let prover = HashChainProver::<F, C, D, H>::with_config(self.config.clone())?;
+        let proof = prover.prove(seed, steps)?;
+        Ok((proof, prover.into_circuit_data()))
+    }
+
     // Setup the recursive hashes structure by establishing the size of the inputs and outputs
     // and connecting them to each other appropriately. Additionally setup the conditional proof
-    // verification depending on whether we are in the base layer or not.
+    // verification depending on whether we are in the base layer or not. The base layer
+    // applies no hash and has a counter of 0; every recursive layer hashes the latest hash
+    // of the inner proof `hashes_per_layer` times and advances its counter by as much.
     fn setup_recursive_layers(
-        &self,
         builder: &mut CircuitBuilder<F, D>,
         common_data: CommonCircuitData<F, D>,
-        initial_hash_target: HashOutTarget,
-        condition: BoolTarget,
-        current_hash_in: HashOutTarget,
-        one: Target,
-        counter: Target,
+        layer: &ChainLayer,
     ) -> Result<ProofWithPublicInputsTarget<D>, HashChainError> {
-        let inner_cyclic_proof_with_pub_inputs = builder.add_virtual_proof_with_pis(&common_data);
-        let inner_cyclic_pub_inputs = &inner_cyclic_proof_with_pub_inputs.public_inputs;
-        let inner_cyclic_initial_hash = HashOutTarget::try_from(&inner_cyclic_pub_inputs[0..4])?;
-        let inner_cyclic_latest_hash = HashOutTarget::try_from(&inner_cyclic_pub_inputs[4..8])?;
-        let inner_cyclic_counter = inner_cyclic_pub_inputs[8];
-        builder.connect_hashes(initial_hash_target, inner_cyclic_initial_hash);
-        let actual_hash_in =
-            builder.select_hash(condition, inner_cyclic_latest_hash, initial_hash_target);
-        builder.connect_hashes(current_hash_in, actual_hash_in);
-        let new_counter = builder.mul_add(condition.target, inner_cyclic_counter, one);
-        builder.connect(counter, new_counter);
+        let inner_cyclic_proof_with_pub_inputs = connect_inner_proof(builder, &common_data, layer)?;
         builder.conditionally_verify_cyclic_proof_or_dummy::<C>(
-            condition,
+            layer.condition,
             &inner_cyclic_proof_with_pub_inputs,
             &common_data,
         )?;
-        builder.print_gate_counts(0);
         Ok(inner_cyclic_proof_with_pub_inputs)
     }
 
-    // Generates the common circuit data config for recursion, starting with the base case,
-    // then generating the configs for the recursive cases.
-    fn common_data_for_recursion() -> CommonCircuitData<F, D> {
-        let config = CircuitConfig::standard_recursion_config();
-        let builder = CircuitBuilder::<F, D>::new(config);
-        let data = builder.build::<C>();
-
-        let config = CircuitConfig::standard_recursion_config();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
-        let proof = builder.add_virtual_proof_with_pis(&data.common);
-        let verifier_data =
-            builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
-        builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
-        let data = builder.build::<C>();
-
-        let config = CircuitConfig::standard_recursion_config();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
-        let proof = builder.add_virtual_proof_with_pis(&data.common);
-        let verifier_data =
-            builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
-        builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
-
-        // Im not entiirely sure why we do this, but my best guess is that FRI requires AIR traces that are powers of 2.
-        // So this step ensures that the builder always has a gate count that is a power of 2.
-        while builder.num_gates() < 1 << 12 {
-            builder.add_gate(NoopGate, vec![]);
+    // Derives the common circuit data for recursion as a fixed point. The cyclic circuit
+    // verifies proofs of itself, so its common data depends on itself: starting from the
+    // common data of an empty circuit, build the layer on top of a verifier for proofs with
+    // the current guess, and take the common data of the result as the next guess. Building
+    // the cyclic circuit itself would check it against the guess, so the inner proof is
+    // verified as an ordinary proof against the layer's own verifier data instead, which
+    // takes the same gates. Each guess fixes the degree of the next, so this settles
+    // within a few builds; if it does not, the layer keeps outgrowing its verifier.
+    fn common_data_for_recursion<T, L>(
+        config: &CircuitConfig,
+        build_layer: &L,
+    ) -> Result<CommonCircuitData<F, D>, HashChainError>
+    where
+        L: Fn(&mut CircuitBuilder<F, D>) -> Result<(ChainLayer, T), HashChainError>,
+    {
+        let mut common_data = CircuitBuilder::<F, D>::new(config.clone())
+            .build::<C>()
+            .common;
+        for iteration in 1..=MAX_SIZING_ITERATIONS {
+            let (mut builder, layer, verifier_data_target, _) = add_layer(config, build_layer)?;
+            common_data.num_public_inputs = builder.num_public_inputs();
+            let inner_proof_with_pub_inputs =
+                connect_inner_proof(&mut builder, &common_data, &layer)?;
+            builder.conditionally_verify_proof_or_dummy::<C>(
+                layer.condition,
+                &inner_proof_with_pub_inputs,
+                &verifier_data_target,
+                &common_data,
+            )?;
+            let next = builder.build::<C>().common;
+            if next == common_data {
+                info!(
+                    "Recursion common data reached a fixed point after {} builds, degree {} = 2^{}",
+                    iteration,
+                    next.degree(),
+                    next.degree_bits()
+                );
+                return Ok(next);
+            }
+            common_data = next;
         }
+        Err(HashChainError::RecursionSizing {
+            iterations: MAX_SIZING_ITERATIONS,
+        })
+    }
 
-        builder.build::<C>().common
+    // Build and compile a cyclic circuit whose layers are built by `build_layer`, on top
+    // of the recursion common data derived for them. `build_layer` is called once per
+    // sizing build and once more for the circuit itself, so it must build the same
+    // layer every time; the targets it returns alongside the layer are those of the
+    // last call.
+    fn build_cyclic_circuit<T, L>(
+        config: &CircuitConfig,
+        build_layer: L,
+    ) -> Result<(CyclicCircuit<F, C, D>, T), HashChainError>
+    where
+        L: Fn(&mut CircuitBuilder<F, D>) -> Result<(ChainLayer, T), HashChainError>,
+    {
+        let common_data =
+            <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::common_data_for_recursion(
+                config,
+                &build_layer,
+            )?;
+        let (mut builder, layer, verifier_data_target, targets) = add_layer(config, &build_layer)?;
+        let inner_cyclic_proof_with_pub_inputs =
+            <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::setup_recursive_layers(
+                &mut builder,
+                common_data,
+                &layer,
+            )?;
+        info!("Number of gates in circuit: {}", builder.num_gates());
+        let circuit = CyclicCircuit {
+            data: builder.build::<C>(),
+            condition: layer.condition,
+            inner_cyclic_proof_with_pub_inputs,
+            verifier_data_target,
+        };
+        Ok((circuit, targets))
     }
 
     // This function is used in the recursive layers to verify the proofs and set
-    // set the inputs.
+    // set the inputs. `private_witness` holds the values of any targets that are not
+    // derived from the inner proof, such as a private seed.
     fn check_cyclic_proof_layer(
         condition: BoolTarget,
         inner_cyclic_proof_with_pub_inputs: ProofWithPublicInputsTarget<D>,
         proof: ProofWithPublicInputs<F, C, D>,
         verifier_data_target: VerifierCircuitTarget,
         cyclic_circuit_data: &CircuitData<F, C, D>,
+        private_witness: &PartialWitness<F>,
     ) -> Result<ProofWithPublicInputs<F, C, D>, HashChainError> {
-        let mut pw = PartialWitness::new();
+        let mut pw = private_witness.clone();
         pw.set_bool_target(condition, true);
         pw.set_proof_with_pis_target(&inner_cyclic_proof_with_pub_inputs, &proof);
         pw.set_verifier_data_target(&verifier_data_target, &cyclic_circuit_data.verifier_only);
@@ -356,26 +502,27 @@ where
     // Verify the previous layer, hash in the current layer, and prove
     fn process_recursive_layer(
         condition: BoolTarget,
-        inner_cyclic_proof_with_pub_inputs: ProofWithPublicInputsTarget<D>,
-        common_data: CommonCircuitData<F, D>,
-        cyclic_circuit_data: CircuitData<F, C, D>,
-        verifier_data_target: VerifierCircuitTarget,
-        steps: usize,
-    ) -> Result<(ProofWithPublicInputs<F, C, D>, CircuitData<F, C, D>), HashChainError> {
-        // Setup the partial witness for the proof, and set the
-        // initial public input wires with an array of field elements set to
-        // the empty hash
-        let mut pw = PartialWitness::new();
-        let initial_hash = [];
-        let initial_hash_pub_inputs = initial_hash.into_iter().enumerate().collect();
+        inner_cyclic_proof_with_pub_inputs: &ProofWithPublicInputsTarget<D>,
+        common_data: &CommonCircuitData<F, D>,
+        cyclic_circuit_data: &CircuitData<F, C, D>,
+        verifier_data_target: &VerifierCircuitTarget,
+        initial_hash: &[F],
+        private_witness: &PartialWitness<F>,
+        layers: usize,
+    ) -> Result<ProofWithPublicInputs<F, C, D>, HashChainError> {
+        // Setup the partial witness for the base proof on top of the private witness,
+        // and set the initial public input wires of the dummy inner proof to the
+        // initial hash: the seed itself, its commitment, or nothing when it is hidden.
+        let mut pw = private_witness.clone();
+        let initial_hash_pub_inputs = initial_hash.iter().copied().enumerate().collect();
 
         // Set the condition wire to false because we are not in the recursive case
         // initially
         pw.set_bool_target(condition, false);
         pw.set_proof_with_pis_target::<C, D>(
-            &inner_cyclic_proof_with_pub_inputs,
+            inner_cyclic_proof_with_pub_inputs,
             &cyclic_base_proof(
-                &common_data,
+                common_data,
                 &cyclic_circuit_data.verifier_only,
                 initial_hash_pub_inputs,
             ),
@@ -389,7 +536,7 @@ where
         );
 
         // Setup the expected data for the verifier
-        pw.set_verifier_data_target(&verifier_data_target, &cyclic_circuit_data.verifier_only);
+        pw.set_verifier_data_target(verifier_data_target, &cyclic_circuit_data.verifier_only);
         let proof = cyclic_circuit_data.prove(pw)?;
         check_cyclic_proof_verifier_data(
             &proof,
@@ -398,82 +545,217 @@ where
         )?;
         cyclic_circuit_data.verify(proof.clone())?;
 
-        // Base case of the recursion
-        let mut proof = Self::check_cyclic_proof_layer(
-            condition,
-            inner_cyclic_proof_with_pub_inputs.clone(),
-            proof,
-            verifier_data_target.clone(),
-            &cyclic_circuit_data,
-        )?;
-        cyclic_circuit_data.verify(proof.clone())?;
-
-        // Subsequent recursive steps
-        for _ in 0..steps {
-            proof = Self::check_cyclic_proof_layer(
+        // Each recursive layer applies the hashes of one layer
+        let mut proof = proof;
+        for _ in 0..layers {
+            proof = <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::check_cyclic_proof_layer(
                 condition,
                 inner_cyclic_proof_with_pub_inputs.clone(),
                 proof,
                 verifier_data_target.clone(),
-                &cyclic_circuit_data,
+                cyclic_circuit_data,
+                private_witness,
             )?;
         }
 
-        Ok((proof, cyclic_circuit_data))
+        Ok(proof)
     }
 
     // Verify a proof given a circuit. This step is carried out by
-    // a verifying party. This circuit is not currently configured
-    // for zero-knowledge and should not be considered private.
+    // a verifying party and is succinct: its cost does not depend on
+    // the number of steps in the chain. Unless the circuit was built with a
+    // zero-knowledge config, the proof should not be considered private.
     fn verify(
         proof: ProofWithPublicInputs<F, C, D>,
         cyclic_circuit_data: &CircuitData<F, C, D>,
     ) -> Result<(), HashChainError> {
-        // Use the given hash permutation from plonky2 to verify
-        // that the repeated hash is computed correctly.
-        let initial_hash = &proof.public_inputs[..4];
-        let hash = &proof.public_inputs[4..8];
-        let counter = proof.public_inputs[8];
-
-        // The verifier would not do this.
-        // verification of the proof is sufficient to be
-        // convinced with high probablity that the proof
-        // is correct, this is merely done to validate
-        // the circuit output.
-        let expected_hash: [F; 4] = iterate_hash(
-            initial_hash.try_into()?,
-            counter.to_canonical_u64() as usize,
-        );
-        assert_eq!(hash, expected_hash);
-
         // Check the size of the proof; this number should remain
         // the same regardless of the number of steps in the
         // recursive circuit.
         let proof_bytes = proof.to_bytes();
         info!("Total Proof length: {} bytes", proof_bytes.len());
+
+        // The verifier data carried in the public inputs must be that of
+        // this circuit, otherwise the recursion could have verified another.
+        check_cyclic_proof_verifier_data(
+            &proof,
+            &cyclic_circuit_data.verifier_only,
+            &cyclic_circuit_data.common,
+        )?;
         Ok(cyclic_circuit_data.verify(proof)?)
     }
+
+    // Verify a proof and check that it covers exactly `steps` hash applications.
+    // The counter is a public input of the proof, so a proof of a shorter or longer
+    // chain verifies just as well under `verify`.
+    fn verify_steps(
+        proof: ProofWithPublicInputs<F, C, D>,
+        cyclic_circuit_data: &CircuitData<F, C, D>,
+        steps: u64,
+    ) -> Result<(), HashChainError> {
+        let actual = ChainPublicInputs::from_proof::<C, D, H>(&proof)?.step_count();
+        if actual != steps {
+            return Err(HashChainError::StepCountMismatch {
+                expected: steps,
+                actual,
+            });
+        }
+        <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::verify(proof, cyclic_circuit_data)
+    }
+
+    // Recompute the chain natively from the initial hash and counter in the
+    // public inputs, then verify the proof. This needs a chain with a public seed;
+    // chains with a committed or hidden seed cannot be audited. The verifier would not do this:
+    // verification of the proof is sufficient to be convinced with high
+    // probablity that the proof is correct. It is O(n) in the length of the
+    // chain and is merely useful to validate the circuit output.
+    fn audit_verify(
+        proof: ProofWithPublicInputs<F, C, D>,
+        cyclic_circuit_data: &CircuitData<F, C, D>,
+    ) -> Result<(), HashChainError> {
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, H>(&proof)?;
+        let initial_hash = public_inputs.initial_state::<D, H>()?;
+        let hash = public_inputs.final_state::<D, H>()?;
+        let counter = public_inputs.step_count();
+
+        let expected_hash = H::iterate(initial_hash, counter as usize);
+        if hash != expected_hash {
+            return Err(HashChainError::ChainMismatch { counter });
+        }
+
+        <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::verify(proof, cyclic_circuit_data)
+    }
+
+    // Compress a final chain proof. The compressed proof carries the same public
+    // inputs and can be shipped in place of the full proof.
+    fn compress(
+        proof: ProofWithPublicInputs<F, C, D>,
+        cyclic_circuit_data: &CircuitData<F, C, D>,
+    ) -> Result<CompressedProofWithPublicInputs<F, C, D>, HashChainError> {
+        let compressed_proof = cyclic_circuit_data.compress(proof)?;
+        info!(
+            "Compressed Proof length: {} bytes",
+            compressed_proof.to_bytes().len()
+        );
+        Ok(compressed_proof)
+    }
+
+    // Verify a compressed proof given a circuit. Like `verify`, but the proof is
+    // decompressed against the circuit's common data before it is checked.
+    fn verify_compressed(
+        compressed_proof: CompressedProofWithPublicInputs<F, C, D>,
+        cyclic_circuit_data: &CircuitData<F, C, D>,
+    ) -> Result<(), HashChainError> {
+        Ok(cyclic_circuit_data.verify_compressed(compressed_proof)?)
+    }
+}
+
+// Apply `hashes` chained hashes to `state` in-circuit.
+pub(crate) fn apply_hashes<F: RichField + Extendable<D>, const D: usize, H: ChainHasher<F, D>>(
+    builder: &mut CircuitBuilder<F, D>,
+    state: Vec<Target>,
+    hashes: usize,
+) -> Vec<Target> {
+    (0..hashes).fold(state, |state, _| H::hash_circuit(builder, &state))
+}
+
+// Start a layer circuit: build the layer with `build_layer`, then register its chain
+// inputs and the verifier data as public inputs.
+fn add_layer<F, const D: usize, T, L>(
+    config: &CircuitConfig,
+    build_layer: &L,
+) -> Result<(CircuitBuilder<F, D>, ChainLayer, VerifierCircuitTarget, T), HashChainError>
+where
+    F: RichField + Extendable<D>,
+    L: Fn(&mut CircuitBuilder<F, D>) -> Result<(ChainLayer, T), HashChainError>,
+{
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    let (mut layer, targets) = build_layer(&mut builder)?;
+    builder.register_public_inputs(&layer.public_inputs.chain_inputs());
+    let verifier_data_target = builder.add_verifier_data_public_inputs();
+    layer
+        .public_inputs
+        .set_verifier_digest(&verifier_data_target);
+    Ok((builder, layer, verifier_data_target, targets))
 }
 
-// Iterate a hash n number of times for validation purposes. 
-fn iterate_hash<F: RichField>(initial_state: [F; 4], n: usize) -> [F; 4] {
-    let mut current = initial_state;
-    for _ in 0..n {
-        current = hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(&current).elements;
+// Add the inner proof of a layer and connect its public inputs to the layer: the initial
+// hash is carried over, the latest hash is fed into the hashes of the layer, and the
+// counter advances by `hashes_per_layer` outside the base case.
+fn connect_inner_proof<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    common_data: &CommonCircuitData<F, D>,
+    layer: &ChainLayer,
+) -> Result<ProofWithPublicInputsTarget<D>, HashChainError> {
+    let inner_cyclic_proof_with_pub_inputs = builder.add_virtual_proof_with_pis(common_data);
+    // The inner proof is of this same circuit, so its public inputs are laid out
+    // like those of the layer.
+    let inner_cyclic_pub_inputs = ChainPublicInputs::from_slice_with_initial_width(
+        &inner_cyclic_proof_with_pub_inputs.public_inputs,
+        layer.public_inputs.initial_hash.len(),
+        layer.public_inputs.final_hash.len(),
+    )?;
+    for (initial, inner_initial) in layer
+        .public_inputs
+        .initial_hash
+        .iter()
+        .zip(&inner_cyclic_pub_inputs.initial_hash)
+    {
+        builder.connect(*initial, *inner_initial);
     }
-    current
+    for (hash_in, inner_latest) in layer
+        .current_hash_in
+        .iter()
+        .zip(&inner_cyclic_pub_inputs.final_hash)
+    {
+        builder.connect(*hash_in, *inner_latest);
+    }
+    let increment = builder.mul_const(
+        F::from_canonical_usize(layer.hashes_per_layer),
+        layer.condition.target,
+    );
+    let new_steps = builder.mul_add(
+        layer.condition.target,
+        inner_cyclic_pub_inputs.steps,
+        increment,
+    );
+    builder.connect(layer.public_inputs.steps, new_steps);
+    Ok(inner_cyclic_proof_with_pub_inputs)
+}
+
+/// Absorb arbitrary bytes into a seed suitable for `build_hash_chain_circuit_with_seed`.
+///
+/// The bytes are packed seven at a time into field elements (so every chunk is
+/// canonical in a 64-bit field), prefixed with the byte length to avoid
+/// ambiguity between inputs that differ only in trailing zeros, and hashed with
+/// Poseidon.
+pub fn seed_from_bytes<F: RichField>(bytes: &[u8]) -> [F; 4] {
+    let mut elements = vec![F::from_canonical_usize(bytes.len())];
+    elements.extend(bytes.chunks(7).map(|chunk| {
+        let mut limb = [0u8; 8];
+        limb[..chunk.len()].copy_from_slice(chunk);
+        F::from_canonical_u64(u64::from_le_bytes(limb))
+    }));
+    hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(&elements).elements
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::HashChain;
+    use crate::{
+        seed_from_bytes, ChainHasher, CyclicProver, HashChain, HashChainError, HashChainProver,
+        PoseidonHasher,
+    };
     use plonky2::{
-        field::goldilocks_field::GoldilocksField,
+        field::{
+            goldilocks_field::GoldilocksField,
+            types::{Field, PrimeField64},
+        },
         plonk::{
             circuit_builder::CircuitBuilder,
             circuit_data::CircuitConfig,
             config::{GenericConfig, PoseidonGoldilocksConfig},
+            proof::CompressedProofWithPublicInputs,
         },
     };
 
@@ -499,4 +781,191 @@ mod tests {
             );
         assert!(result.is_ok())
     }
+
+    #[test]
+    fn test_hash_chain_with_seed() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let seeds: [[F; 4]; 2] = [
+            [
+                F::ONE,
+                F::TWO,
+                F::from_canonical_u64(3),
+                F::from_canonical_u64(4),
+            ],
+            seed_from_bytes(b"hash chain seed"),
+        ];
+
+        let mut final_hashes = Vec::new();
+        for seed in seeds {
+            let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
+            let (proof, circuit_map) = <CircuitBuilder<GoldilocksField, D> as HashChain<
+                GoldilocksField,
+                D,
+                C,
+            >>::build_hash_chain_circuit_with_seed(
+                &mut circuit, seed, 2
+            )
+            .unwrap();
+
+            assert_eq!(proof.public_inputs[0..4], seed);
+            final_hashes.push(proof.public_inputs[4..8].to_vec());
+
+            let result =
+                <CircuitBuilder<GoldilocksField, D> as HashChain<GoldilocksField, D, C>>::verify(
+                    proof,
+                    &circuit_map,
+                );
+            assert!(result.is_ok())
+        }
+        assert_ne!(final_hashes[0], final_hashes[1]);
+    }
+
+    #[test]
+    fn test_seed_from_bytes() {
+        type F = GoldilocksField;
+
+        assert_eq!(seed_from_bytes::<F>(b"abc"), seed_from_bytes::<F>(b"abc"));
+        assert_ne!(seed_from_bytes::<F>(b"abc"), seed_from_bytes::<F>(b"abd"));
+        assert_ne!(seed_from_bytes::<F>(b"abc"), seed_from_bytes::<F>(b"abc\0"));
+    }
+
+    #[test]
+    fn test_compressed_hash_chain() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
+        let (proof, circuit_map) = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+        >>::build_hash_chain_circuit(&mut circuit, 2)
+        .unwrap();
+        let proof_length = proof.to_bytes().len();
+
+        let compressed_proof = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+        >>::compress(proof.clone(), &circuit_map)
+        .unwrap();
+
+        // Round trip the compressed proof through its byte encoding.
+        let compressed_bytes = compressed_proof.to_bytes();
+        assert!(compressed_bytes.len() < proof_length);
+        let compressed_proof = CompressedProofWithPublicInputs::<F, C, D>::from_bytes(
+            compressed_bytes,
+            &circuit_map.common,
+        )
+        .unwrap();
+        assert_eq!(compressed_proof.public_inputs, proof.public_inputs);
+
+        let result = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+        >>::verify_compressed(compressed_proof, &circuit_map);
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn test_audit_verify() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
+        let (proof, circuit_map) = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+        >>::build_hash_chain_circuit(&mut circuit, 2)
+        .unwrap();
+
+        let result =
+            <CircuitBuilder<GoldilocksField, D> as HashChain<GoldilocksField, D, C>>::audit_verify(
+                proof.clone(),
+                &circuit_map,
+            );
+        assert!(result.is_ok());
+
+        // A final hash that disagrees with the native chain is reported, not panicked on.
+        let mut tampered_proof = proof;
+        tampered_proof.public_inputs[4] += F::ONE;
+        let result =
+            <CircuitBuilder<GoldilocksField, D> as HashChain<GoldilocksField, D, C>>::audit_verify(
+                tampered_proof,
+                &circuit_map,
+            );
+        assert!(matches!(result, Err(HashChainError::ChainMismatch { .. })));
+    }
+
+    #[test]
+    fn test_exact_step_count() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let prover = HashChainProver::<F, C, D>::new().unwrap();
+        let seed = seed_from_bytes::<F>(b"exact steps");
+        for steps in [0, 1, 5] {
+            let proof = prover.prove(seed, steps).unwrap();
+            assert_eq!(proof.public_inputs[8].to_canonical_u64(), steps as u64);
+            let expected = <PoseidonHasher as ChainHasher<F, D>>::iterate(seed, steps);
+            assert_eq!(proof.public_inputs[4..8], expected);
+
+            let result = <CircuitBuilder<GoldilocksField, D> as HashChain<
+                GoldilocksField,
+                D,
+                C,
+            >>::verify_steps(proof.clone(), prover.circuit_data(), steps as u64);
+            assert!(result.is_ok());
+
+            let result = <CircuitBuilder<GoldilocksField, D> as HashChain<
+                GoldilocksField,
+                D,
+                C,
+            >>::verify_steps(proof, prover.circuit_data(), steps as u64 + 1);
+            assert!(matches!(
+                result,
+                Err(HashChainError::StepCountMismatch { expected, actual })
+                    if expected == steps as u64 + 1 && actual == steps as u64
+            ));
+        }
+    }
+
+    #[test]
+    fn test_recursion_sizing() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // A layer of a thousand hashes outgrows the degree of a single hash. The
+        // recursion common data is derived for it all the same, and the chain proves.
+        let single = HashChainProver::<F, C, D>::new().unwrap();
+        let batched = HashChainProver::<F, C, D>::with_hashes_per_layer(
+            CircuitConfig::standard_recursion_config(),
+            1024,
+        )
+        .unwrap();
+        assert!(
+            batched.circuit_data().common.degree_bits()
+                > single.circuit_data().common.degree_bits()
+        );
+
+        let seed = seed_from_bytes::<F>(b"sized chain");
+        let proof = batched.prove(seed, 1024).unwrap();
+        assert_eq!(
+            proof.public_inputs[4..8],
+            <PoseidonHasher as ChainHasher<F, D>>::iterate(seed, 1024)
+        );
+        assert!(batched.verifier().verify(proof).is_ok());
+    }
 }
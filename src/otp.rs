@@ -0,0 +1,263 @@
+//! Hash-chain one-time passwords, S/KEY style.
+//!
+//! A client picks a secret x and enrolls the anchor H^n(x) with the server. The i-th
+//! login presents the one-time password H^(n-i)(x), which the server accepts if
+//! hashing it lands on the current anchor, and which then becomes the new anchor.
+//!
+//! Both checks are replaced by succinct proofs here:
+//!
+//! - a knowledge proof shows "I know x with H^n(x) = anchor" without revealing x.
+//!   It is a zero-knowledge chain with a tagged seed, and lets a client enroll or
+//!   rotate to a fresh anchor. The tag binds the proof to its enrollment: anyone
+//!   can extend a proof for the anchor A into one for H(A) without knowing x, but
+//!   the extended proof still carries the tag of the enrollment of A;
+//! - a presentation proof shows H^k(v) = anchor for a presented value v and a
+//!   public k, so a login that skipped k - 1 passwords costs the server no more
+//!   than one that did not.
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    plonk::{
+        circuit_data::CircuitConfig,
+        config::{AlgebraicHasher, GenericConfig},
+    },
+};
+
+use crate::{
+    hasher::ChainHasher,
+    prover::HashChainProver,
+    seed_from_bytes,
+    verifier::HashChainVerifier,
+    zk::{SeedVisibility, SEED_SALT_WIDTH},
+    ChainPublicInputs, CyclicProver, HashChainError, PoseidonHasher, Proof,
+};
+
+/// The one-time password at `depth` of the chain from `secret`, H^depth(secret). The
+/// anchor of a chain of `n` passwords is the one at depth `n`.
+pub fn one_time_password<F: RichField + Extendable<D>, const D: usize, H: ChainHasher<F, D>>(
+    secret: H::State,
+    depth: usize,
+) -> H::State {
+    H::iterate(secret, depth)
+}
+
+// The tag of knowledge proofs for the enrollment `enrollment_id`.
+fn enrollment_tag<F: RichField>(enrollment_id: &[u8]) -> [F; SEED_SALT_WIDTH] {
+    seed_from_bytes(enrollment_id)
+}
+
+pub struct OtpProver<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+    H: ChainHasher<F, D> = PoseidonHasher,
+> {
+    knowledge: HashChainProver<F, C, D, H>,
+    presentation: HashChainProver<F, C, D, H>,
+}
+
+impl<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + 'static,
+        const D: usize,
+        H: ChainHasher<F, D>,
+    > OtpProver<F, C, D, H>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Build the knowledge and presentation circuits with the standard recursion
+    /// config.
+    pub fn new() -> Result<Self, HashChainError> {
+        Self::with_config(CircuitConfig::standard_recursion_config())
+    }
+
+    /// Build the knowledge and presentation circuits with `config`. The knowledge
+    /// circuit is always zero-knowledge.
+    pub fn with_config(config: CircuitConfig) -> Result<Self, HashChainError> {
+        let mut knowledge_config = config.clone();
+        knowledge_config.zero_knowledge = true;
+        Ok(Self {
+            knowledge: HashChainProver::with_seed_visibility(
+                knowledge_config,
+                SeedVisibility::Tagged,
+            )?,
+            presentation: HashChainProver::with_config(config)?,
+        })
+    }
+
+    /// Prove knowledge of `secret` such that H^depth(secret) is the anchor, for the
+    /// enrollment `enrollment_id`. The proof carries the anchor, `depth` and a tag of
+    /// the enrollment id, but not the secret. The enrollment id should be unique to
+    /// the enrollment, such as a nonce the server hands out for it. `depth` must be
+    /// at least one, as the anchor at depth 0 is the secret itself.
+    pub fn prove_knowledge(
+        &self,
+        secret: H::State,
+        depth: usize,
+        enrollment_id: &[u8],
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        self.knowledge
+            .prove_with_salt(secret, enrollment_tag(enrollment_id), depth)
+    }
+
+    /// Prove that H^k(value) is the anchor, for a presented one-time password `value`.
+    pub fn prove_presentation(
+        &self,
+        value: H::State,
+        k: usize,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        self.presentation.prove(value, k)
+    }
+
+    /// A verifier for the proofs of this prover.
+    pub fn verifier(&self) -> OtpVerifier<F, C, D> {
+        OtpVerifier {
+            knowledge: self.knowledge.verifier(),
+            presentation: self.presentation.verifier(),
+        }
+    }
+}
+
+pub struct OtpVerifier<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    knowledge: HashChainVerifier<F, C, D>,
+    presentation: HashChainVerifier<F, C, D>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    OtpVerifier<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Verify a knowledge proof of `H` for the preimage at `depth` of `anchor`, made
+    /// for the enrollment `enrollment_id`.
+    pub fn verify_knowledge<H: ChainHasher<F, D>>(
+        &self,
+        proof: Proof<F, C, D>,
+        anchor: &H::State,
+        depth: u64,
+        enrollment_id: &[u8],
+    ) -> Result<(), HashChainError> {
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, H>(&proof)?;
+        if public_inputs.initial_hash != enrollment_tag::<F>(enrollment_id) {
+            return Err(HashChainError::EnrollmentMismatch);
+        }
+        if public_inputs.final_hash != H::state_to_public_inputs(anchor) {
+            return Err(HashChainError::AnchorMismatch);
+        }
+        self.knowledge.verify_steps::<H>(proof, depth)
+    }
+
+    /// Verify a presentation proof of `H` that H^k(value) is `anchor`. On success the
+    /// server moves its anchor to `value`, so that the proof cannot be replayed.
+    pub fn verify_presentation<H: ChainHasher<F, D>>(
+        &self,
+        proof: Proof<F, C, D>,
+        value: &H::State,
+        anchor: &H::State,
+        k: u64,
+    ) -> Result<(), HashChainError> {
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, H>(&proof)?;
+        if public_inputs.initial_hash != H::state_to_public_inputs(value) {
+            return Err(HashChainError::PresentedValueMismatch);
+        }
+        if public_inputs.final_hash != H::state_to_public_inputs(anchor) {
+            return Err(HashChainError::AnchorMismatch);
+        }
+        self.presentation.verify_steps::<H>(proof, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        otp::{one_time_password, OtpProver},
+        seed_from_bytes, HashChainError, PoseidonHasher,
+    };
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_otp_rotation() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = PoseidonHasher;
+
+        let prover = OtpProver::<F, C, D>::new().unwrap();
+        let verifier = prover.verifier();
+
+        // Enrollment: the client proves it holds the preimage of its anchor.
+        let secret = seed_from_bytes::<F>(b"otp secret");
+        let mut anchor = one_time_password::<F, D, H>(secret, 3);
+        // The anchor at depth 0 is the secret, which a proof must not publish.
+        assert!(matches!(
+            prover.prove_knowledge(secret, 0, b"enrollment 1"),
+            Err(HashChainError::EmptyPrivateChain)
+        ));
+        let proof = prover.prove_knowledge(secret, 3, b"enrollment 1").unwrap();
+        assert!(verifier
+            .verify_knowledge::<H>(proof.clone(), &anchor, 3, b"enrollment 1")
+            .is_ok());
+        assert!(matches!(
+            verifier.verify_knowledge::<H>(proof, &secret, 3, b"enrollment 1"),
+            Err(HashChainError::AnchorMismatch)
+        ));
+
+        // Logins walk the chain back towards the secret. The second login skips a
+        // password and presents one two steps below the anchor.
+        for (depth, k) in [(2, 1), (0, 2)] {
+            let value = one_time_password::<F, D, H>(secret, depth);
+            let proof = prover.prove_presentation(value, k).unwrap();
+            assert!(verifier
+                .verify_presentation::<H>(proof.clone(), &value, &anchor, k as u64)
+                .is_ok());
+            assert!(matches!(
+                verifier.verify_presentation::<H>(proof.clone(), &anchor, &anchor, k as u64),
+                Err(HashChainError::PresentedValueMismatch)
+            ));
+            anchor = value;
+
+            // A replayed login no longer ends at the anchor.
+            assert!(matches!(
+                verifier.verify_presentation::<H>(proof, &value, &anchor, k as u64),
+                Err(HashChainError::AnchorMismatch)
+            ));
+        }
+
+        // The chain is used up, so the client rotates to a fresh secret.
+        let secret = seed_from_bytes::<F>(b"rotated otp secret");
+        let anchor = one_time_password::<F, D, H>(secret, 2);
+        let proof = prover.prove_knowledge(secret, 2, b"enrollment 2").unwrap();
+        assert!(verifier
+            .verify_knowledge::<H>(proof, &anchor, 2, b"enrollment 2")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_extended_knowledge_proof() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = PoseidonHasher;
+
+        let prover = OtpProver::<F, C, D>::new().unwrap();
+        let verifier = prover.verifier();
+
+        let secret = seed_from_bytes::<F>(b"otp secret");
+        let anchor = one_time_password::<F, D, H>(secret, 2);
+        let proof = prover.prove_knowledge(secret, 2, b"victim").unwrap();
+        assert!(verifier
+            .verify_knowledge::<H>(proof.clone(), &anchor, 2, b"victim")
+            .is_ok());
+
+        // Without the secret, anyone can extend the proof to one for H(anchor). It
+        // still carries the tag of the original enrollment, so it cannot enroll
+        // H(anchor) for anyone else.
+        let extended = prover.knowledge.extend(proof, 1).unwrap();
+        let extended_anchor = one_time_password::<F, D, H>(anchor, 1);
+        assert!(matches!(
+            verifier.verify_knowledge::<H>(extended, &extended_anchor, 3, b"attacker"),
+            Err(HashChainError::EnrollmentMismatch)
+        ));
+    }
+}
@@ -0,0 +1,379 @@
+//! Proof-of-History style chains with events mixed in at given counter values.
+//!
+//! Most steps are plain Poseidon steps h' = H(h). At the counter values of the
+//! events, the step instead mixes in the event digest, h' = H(|e| || h || e), and
+//! folds the pair into a running event commitment, a' = H(a || index || e). The
+//! length prefix |e| keeps an event step apart from a plain one: the sponge is
+//! unpadded, so without it an event with an all-zero digest would hash exactly like
+//! a plain step. The proof thus
+//! attests that the events happened in the committed order, with `index` hashes
+//! done before each of them, as in Solana's Proof of History.
+//!
+//! The chain state is the head followed by the event commitment, so the public
+//! inputs follow `ChainPublicInputs` with a state `POH_STATE_WIDTH` elements wide:
+//!
+//! ```text
+//! initial hash  [seed (4), zero (4)]
+//! final hash    [head (4), event commitment (4)]
+//! steps         number of hashes, events included
+//! ```
+use plonky2::{
+    field::{extension::Extendable, types::Field},
+    hash::{
+        hash_types::{RichField, NUM_HASH_OUT_ELTS},
+        hashing::hash_n_to_hash_no_pad,
+        poseidon::{PoseidonHash, PoseidonPermutation},
+    },
+    iop::{
+        target::{BoolTarget, Target},
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::CircuitConfig,
+        config::{AlgebraicHasher, GenericConfig},
+    },
+};
+
+use crate::{
+    seed_from_bytes, verifier::HashChainVerifier, ChainLayer, ChainPublicInputs, CyclicCircuit,
+    CyclicProver, HashChain, HashChainError, PoseidonHasher, Proof,
+};
+
+/// Number of field elements in the state of a PoH chain: the head and the event
+/// commitment.
+pub const POH_STATE_WIDTH: usize = 2 * NUM_HASH_OUT_ELTS;
+
+/// An event mixed into the chain: the counter value at which it is mixed in, and
+/// its digest.
+pub type PohEvent<F> = (u64, [F; NUM_HASH_OUT_ELTS]);
+
+/// The digest of an event given as bytes.
+pub fn event_digest<F: RichField>(event: &[u8]) -> [F; NUM_HASH_OUT_ELTS] {
+    seed_from_bytes(event)
+}
+
+/// Fold the event `digest` mixed in at counter value `index` into `commitment`.
+pub fn mix_event<F: RichField>(
+    commitment: &[F; NUM_HASH_OUT_ELTS],
+    index: u64,
+    digest: &[F; NUM_HASH_OUT_ELTS],
+) -> [F; NUM_HASH_OUT_ELTS] {
+    let mut inputs = commitment.to_vec();
+    inputs.push(F::from_canonical_u64(index));
+    inputs.extend_from_slice(digest);
+    hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(&inputs).elements
+}
+
+/// The event commitment of a chain with `events`, in order.
+pub fn event_commitment<F: RichField>(events: &[PohEvent<F>]) -> [F; NUM_HASH_OUT_ELTS] {
+    events.iter().fold(
+        [F::ZERO; NUM_HASH_OUT_ELTS],
+        |commitment, (index, digest)| mix_event(&commitment, *index, digest),
+    )
+}
+
+/// The head of the PoH chain of `steps` hashes from `seed` with `events` mixed in,
+/// computed natively.
+pub fn poh_head<F: RichField>(
+    seed: [F; NUM_HASH_OUT_ELTS],
+    steps: u64,
+    events: &[PohEvent<F>],
+) -> [F; NUM_HASH_OUT_ELTS] {
+    let mut events = events.iter().peekable();
+    let mut head = seed;
+    for counter in 0..steps {
+        let inputs = match events.next_if(|(index, _)| *index == counter) {
+            Some((_, digest)) => event_step_inputs(&head, digest),
+            None => head.to_vec(),
+        };
+        head = hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(&inputs).elements;
+    }
+    head
+}
+
+// The inputs of the step that mixes `digest` into `head`, prefixed with the length
+// of the digest.
+fn event_step_inputs<F: RichField>(
+    head: &[F; NUM_HASH_OUT_ELTS],
+    digest: &[F; NUM_HASH_OUT_ELTS],
+) -> Vec<F> {
+    let mut inputs = vec![F::from_canonical_usize(NUM_HASH_OUT_ELTS)];
+    inputs.extend_from_slice(head);
+    inputs.extend_from_slice(digest);
+    inputs
+}
+
+/// Builds the cyclic circuit for PoH chains once, and proves any number of chains
+/// against it.
+pub struct PohProver<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    circuit: CyclicCircuit<F, C, D>,
+    is_event: BoolTarget,
+    event_target: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    PohProver<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Build and compile the cyclic circuit for PoH chains with the standard
+    /// recursion config.
+    pub fn new() -> Result<Self, HashChainError> {
+        Self::with_config(CircuitConfig::standard_recursion_config())
+    }
+
+    /// Build and compile the cyclic circuit for PoH chains with `config`.
+    pub fn with_config(config: CircuitConfig) -> Result<Self, HashChainError> {
+        let (circuit, (is_event, event_target)) =
+            <CircuitBuilder<F, D> as HashChain<F, D, C, PoseidonHasher>>::build_cyclic_circuit(
+                &config,
+                Self::build_layer,
+            )?;
+
+        Ok(Self {
+            circuit,
+            is_event,
+            event_target,
+        })
+    }
+
+    // Build one layer of the cyclic circuit, returning it with the event flag and
+    // event digest targets.
+    fn build_layer(
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Result<(ChainLayer, (BoolTarget, Vec<Target>)), HashChainError> {
+        let initial_hash_target = builder.add_virtual_targets(POH_STATE_WIDTH);
+        let condition = builder.add_virtual_bool_target_safe();
+        let steps = builder.add_virtual_target();
+
+        // Each layer either hashes the head of the inner proof, or mixes in an event
+        // and folds it into the event commitment. The event is mixed in at the
+        // counter value of the inner proof, one less than this layer's.
+        let current_hash_in = builder.add_virtual_targets(POH_STATE_WIDTH);
+        let (head_in, commitment_in) = current_hash_in.split_at(NUM_HASH_OUT_ELTS);
+        let is_event = builder.add_virtual_bool_target_safe();
+        let event_target = builder.add_virtual_targets(NUM_HASH_OUT_ELTS);
+        let index = builder.add_const(steps, F::NEG_ONE);
+
+        let plain_head = builder
+            .hash_n_to_hash_no_pad::<PoseidonHash>(head_in.to_vec())
+            .elements;
+        let event_length = builder.constant(F::from_canonical_usize(NUM_HASH_OUT_ELTS));
+        let event_head = builder
+            .hash_n_to_hash_no_pad::<PoseidonHash>(
+                [event_length]
+                    .iter()
+                    .chain(head_in)
+                    .chain(&event_target)
+                    .copied()
+                    .collect(),
+            )
+            .elements;
+        let mixed_commitment = builder
+            .hash_n_to_hash_no_pad::<PoseidonHash>(
+                commitment_in
+                    .iter()
+                    .chain([&index])
+                    .chain(&event_target)
+                    .copied()
+                    .collect(),
+            )
+            .elements;
+
+        let mut current_hash_out: Vec<Target> = event_head
+            .iter()
+            .zip(&plain_head)
+            .map(|(event, plain)| builder.select(is_event, *event, *plain))
+            .collect();
+        for (mixed, kept) in mixed_commitment.iter().zip(commitment_in) {
+            current_hash_out.push(builder.select(is_event, *mixed, *kept));
+        }
+        let latest_hash: Vec<Target> = current_hash_out
+            .iter()
+            .zip(&initial_hash_target)
+            .map(|(hash_out, initial)| builder.select(condition, *hash_out, *initial))
+            .collect();
+
+        let layer = ChainLayer {
+            public_inputs: ChainPublicInputs {
+                initial_hash: initial_hash_target,
+                final_hash: latest_hash,
+                steps,
+                verifier_digest: Vec::new(),
+            },
+            condition,
+            current_hash_in,
+            hashes_per_layer: 1,
+        };
+        Ok((layer, (is_event, event_target)))
+    }
+
+    /// Prove a PoH chain of `steps` hashes from `seed`, mixing in each event at its
+    /// counter value. Events must be sorted by strictly increasing counter values
+    /// below `steps`.
+    pub fn prove(
+        &self,
+        seed: [F; NUM_HASH_OUT_ELTS],
+        steps: u64,
+        events: &[PohEvent<F>],
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        let in_order = events.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        if !in_order || events.last().is_some_and(|(index, _)| *index >= steps) {
+            return Err(anyhow::Error::msg(format!(
+                "Events must have strictly increasing counter values below {}.",
+                steps
+            ))
+            .into());
+        }
+
+        let mut initial_hash = seed.to_vec();
+        initial_hash.extend_from_slice(&[F::ZERO; NUM_HASH_OUT_ELTS]);
+        let mut proof =
+            <CircuitBuilder<F, D> as HashChain<F, D, C, PoseidonHasher>>::process_recursive_layer(
+                self.circuit.condition,
+                &self.circuit.inner_cyclic_proof_with_pub_inputs,
+                &self.circuit.data.common,
+                &self.circuit.data,
+                &self.circuit.verifier_data_target,
+                &initial_hash,
+                &self.event_witness(None),
+                0,
+            )?;
+
+        let mut events = events.iter().peekable();
+        for counter in 0..steps {
+            let event = events.next_if(|(index, _)| *index == counter);
+            proof = <CircuitBuilder<F, D> as HashChain<F, D, C, PoseidonHasher>>::check_cyclic_proof_layer(
+                self.circuit.condition,
+                self.circuit.inner_cyclic_proof_with_pub_inputs.clone(),
+                proof,
+                self.circuit.verifier_data_target.clone(),
+                &self.circuit.data,
+                &self.event_witness(event.map(|(_, digest)| digest)),
+            )?;
+        }
+        Ok(proof)
+    }
+
+    // Witness for the event targets of a layer, which mixes in `event` if any.
+    fn event_witness(&self, event: Option<&[F; NUM_HASH_OUT_ELTS]>) -> PartialWitness<F> {
+        let mut pw = PartialWitness::new();
+        pw.set_bool_target(self.is_event, event.is_some());
+        let digest = event.copied().unwrap_or([F::ZERO; NUM_HASH_OUT_ELTS]);
+        for (target, value) in self.event_target.iter().zip(digest) {
+            pw.set_target(*target, value);
+        }
+        pw
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    CyclicProver<F, C, D> for PohProver<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    fn cyclic_circuit(&self) -> &CyclicCircuit<F, C, D> {
+        &self.circuit
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    HashChainVerifier<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Verify a PoH chain proof of `steps` hashes from `seed`, and check that it
+    /// commits to exactly `events`, in order. Returns the head of the chain.
+    pub fn verify_events(
+        &self,
+        proof: Proof<F, C, D>,
+        seed: [F; NUM_HASH_OUT_ELTS],
+        events: &[PohEvent<F>],
+        steps: u64,
+    ) -> Result<[F; NUM_HASH_OUT_ELTS], HashChainError> {
+        let public_inputs = ChainPublicInputs::from_proof_with_width(&proof, POH_STATE_WIDTH)?;
+        let actual = public_inputs.step_count();
+        if actual != steps {
+            return Err(HashChainError::StepCountMismatch {
+                expected: steps,
+                actual,
+            });
+        }
+
+        let (initial_head, initial_commitment) =
+            public_inputs.initial_hash.split_at(NUM_HASH_OUT_ELTS);
+        let (head, commitment) = public_inputs.final_hash.split_at(NUM_HASH_OUT_ELTS);
+        if initial_head != seed
+            || initial_commitment.iter().any(|element| !element.is_zero())
+            || commitment != event_commitment(events)
+        {
+            return Err(HashChainError::EventMismatch {
+                events: events.len(),
+            });
+        }
+
+        self.verify(proof)?;
+        Ok(head.try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        poh::{event_digest, poh_head, PohEvent, PohProver},
+        seed_from_bytes, CyclicProver, HashChainError,
+    };
+    use plonky2::{
+        field::types::Field,
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+
+    #[test]
+    fn test_poh_events() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let prover = PohProver::<F, C, D>::new().unwrap();
+        let verifier = prover.verifier();
+        let seed = seed_from_bytes::<F>(b"proof of history");
+        let deposit = event_digest::<F>(b"deposit");
+        let withdrawal = event_digest::<F>(b"withdrawal");
+        let events: [PohEvent<F>; 2] = [(1, deposit), (3, withdrawal)];
+
+        let proof = prover.prove(seed, 5, &events).unwrap();
+        let head = verifier
+            .verify_events(proof.clone(), seed, &events, 5)
+            .unwrap();
+        assert_eq!(head, poh_head(seed, 5, &events));
+        assert_ne!(head, poh_head(seed, 5, &[]));
+        // An event with an all-zero digest still leaves its mark on the head.
+        let empty_event: PohEvent<F> = (2, [F::ZERO; 4]);
+        assert_ne!(poh_head(seed, 5, &[empty_event]), poh_head(seed, 5, &[]));
+
+        // Reordered, moved or dropped events do not match the commitment.
+        let tampered_events: [Vec<PohEvent<F>>; 3] = [
+            vec![(1, withdrawal), (3, deposit)],
+            vec![(1, deposit), (2, withdrawal)],
+            vec![(1, deposit)],
+        ];
+        for tampered in tampered_events {
+            assert!(matches!(
+                verifier.verify_events(proof.clone(), seed, &tampered, 5),
+                Err(HashChainError::EventMismatch { .. })
+            ));
+        }
+        assert!(matches!(
+            verifier.verify_events(proof, seed, &events, 4),
+            Err(HashChainError::StepCountMismatch { .. })
+        ));
+
+        // Events out of order, or past the end of the chain, cannot be proven.
+        assert!(prover
+            .prove(seed, 5, &[(3, deposit), (1, withdrawal)])
+            .is_err());
+        assert!(prover.prove(seed, 3, &events).is_err());
+    }
+}
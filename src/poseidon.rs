@@ -0,0 +1,51 @@
+//! Poseidon hash chain, the default `ChainHasher`.
+//!
+//! The chain state is a single Poseidon `HashOut`, i.e. four field elements, so the
+//! state occupies public inputs `[0..4]` and `[4..8]` and the counter sits at `[8]`.
+use plonky2::{
+    field::extension::Extendable,
+    hash::{
+        hash_types::RichField,
+        hashing::hash_n_to_hash_no_pad,
+        poseidon::{PoseidonHash, PoseidonPermutation},
+    },
+    iop::target::Target,
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{hasher::ChainHasher, HashChainError};
+
+/// Number of field elements in a Poseidon hash.
+pub const POSEIDON_STATE_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoseidonHasher;
+
+impl<F: RichField + Extendable<D>, const D: usize> ChainHasher<F, D> for PoseidonHasher {
+    type State = [F; POSEIDON_STATE_WIDTH];
+
+    const STATE_WIDTH: usize = POSEIDON_STATE_WIDTH;
+
+    fn add_virtual_state(builder: &mut CircuitBuilder<F, D>) -> Vec<Target> {
+        builder.add_virtual_hash().elements.to_vec()
+    }
+
+    fn hash_circuit(builder: &mut CircuitBuilder<F, D>, state: &[Target]) -> Vec<Target> {
+        builder
+            .hash_n_to_hash_no_pad::<PoseidonHash>(state.to_vec())
+            .elements
+            .to_vec()
+    }
+
+    fn hash(state: &Self::State) -> Self::State {
+        hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(state).elements
+    }
+
+    fn state_to_public_inputs(state: &Self::State) -> Vec<F> {
+        state.to_vec()
+    }
+
+    fn state_from_public_inputs(inputs: &[F]) -> Result<Self::State, HashChainError> {
+        Ok(inputs.try_into()?)
+    }
+}
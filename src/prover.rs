@@ -0,0 +1,573 @@
+//! Reusable prover for hash chains.
+//!
+//! Building the cyclic circuit dominates the cost of a short chain: deriving the
+//! recursion common data alone compiles the circuit a few times over, and the cyclic
+//! circuit is compiled on top of that. A `HashChainProver` does this once and keeps the compiled
+//! circuit and its targets, so any number of chains can then be proven against the
+//! same circuit, and checked by the same verifier data.
+use std::time::{Duration, Instant};
+
+use log::info;
+use plonky2::{
+    field::{extension::Extendable, types::Field},
+    hash::hash_types::{RichField, NUM_HASH_OUT_ELTS},
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::CircuitConfig,
+        config::{AlgebraicHasher, GenericConfig},
+    },
+    recursion::cyclic_recursion::check_cyclic_proof_verifier_data,
+    util::serialization::DefaultGateSerializer,
+};
+
+use crate::{
+    apply_hashes,
+    hasher::ChainHasher,
+    telemetry::ChainTelemetry,
+    zk::{seed_commitment, seed_commitment_circuit, SeedVisibility, SEED_SALT_WIDTH},
+    ChainLayer, ChainPublicInputs, CircuitMap, CyclicCircuit, CyclicProver, HashChain,
+    HashChainError, PoseidonHasher, Proof,
+};
+
+/// The largest number of hashes a single recursion layer can apply.
+pub const MAX_HASHES_PER_LAYER: usize = 1024;
+
+/// Builds the cyclic circuit for chains of `H` once, and proves any number of
+/// chains against it.
+pub struct HashChainProver<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+    H: ChainHasher<F, D> = PoseidonHasher,
+> {
+    circuit: CyclicCircuit<F, C, D>,
+    seed_visibility: SeedVisibility,
+    hashes_per_layer: usize,
+    circuit_build_time: Duration,
+    seed_target: Vec<Target>,
+    salt_target: Vec<Target>,
+    seed: H::State,
+    salt: [F; SEED_SALT_WIDTH],
+    steps: usize,
+}
+
+impl<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + 'static,
+        const D: usize,
+        H: ChainHasher<F, D>,
+    > HashChainProver<F, C, D, H>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Build and compile the cyclic circuit for chains of `H` with the standard
+    /// recursion config.
+    pub fn new() -> Result<Self, HashChainError> {
+        Self::with_config(CircuitConfig::standard_recursion_config())
+    }
+
+    /// Build and compile the cyclic circuit for chains of `H` with `config`. The
+    /// recursion common data is built with the same config.
+    pub fn with_config(config: CircuitConfig) -> Result<Self, HashChainError> {
+        Self::with_seed_visibility(config, SeedVisibility::Public)
+    }
+
+    /// Like `with_config`, for chains whose seed appears in the public inputs as set
+    /// by `seed_visibility`. A private seed only hides anything if `config` is
+    /// zero-knowledge.
+    pub fn with_seed_visibility(
+        config: CircuitConfig,
+        seed_visibility: SeedVisibility,
+    ) -> Result<Self, HashChainError> {
+        Self::with_layout(config, seed_visibility, 1)
+    }
+
+    /// Like `with_config`, for a circuit whose layers each apply `hashes_per_layer`
+    /// hashes, between 1 and `MAX_HASHES_PER_LAYER`. Every layer pays for one
+    /// recursive verification however many hashes it applies, so batching them
+    /// raises the throughput in hashes per second, at the cost of a larger circuit
+    /// and of chain lengths that must be multiples of `hashes_per_layer`.
+    pub fn with_hashes_per_layer(
+        config: CircuitConfig,
+        hashes_per_layer: usize,
+    ) -> Result<Self, HashChainError> {
+        Self::with_layout(config, SeedVisibility::Public, hashes_per_layer)
+    }
+
+    // Build and compile the cyclic circuit for the given seed visibility and number
+    // of hashes per layer.
+    pub(crate) fn with_layout(
+        config: CircuitConfig,
+        seed_visibility: SeedVisibility,
+        hashes_per_layer: usize,
+    ) -> Result<Self, HashChainError> {
+        if !(1..=MAX_HASHES_PER_LAYER).contains(&hashes_per_layer) {
+            return Err(anyhow::Error::msg(format!(
+                "Hashes per layer must be between 1 and {}, got {}.",
+                MAX_HASHES_PER_LAYER, hashes_per_layer
+            ))
+            .into());
+        }
+
+        // Build the cyclic circuit on top of the recursion common data derived for
+        // its layers.
+        let start_time = Instant::now();
+        let (circuit, (seed_target, salt_target)) =
+            <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::build_cyclic_circuit(
+                &config,
+                |builder| Self::build_layer(builder, seed_visibility, hashes_per_layer),
+            )?;
+        let circuit_build_time = start_time.elapsed();
+        let common_data = &circuit.data.common;
+        let common_data_bytes = common_data
+            .to_bytes(&DefaultGateSerializer)
+            .map_err(|_| HashChainError::SerializationError("CommonCircuitData"))?;
+        info!(
+            "Common circuit data length: {} bytes",
+            common_data_bytes.len()
+        );
+        info!(
+            "Initial {} degree {} = 2^{}",
+            "proof",
+            common_data.degree(),
+            common_data.degree_bits()
+        );
+
+        Ok(Self {
+            circuit,
+            seed_visibility,
+            hashes_per_layer,
+            circuit_build_time,
+            seed_target,
+            salt_target,
+            seed: H::State::default(),
+            salt: [F::ZERO; SEED_SALT_WIDTH],
+            steps: 0,
+        })
+    }
+
+    // Build one layer of the cyclic circuit, returning it with the seed and salt
+    // targets.
+    fn build_layer(
+        builder: &mut CircuitBuilder<F, D>,
+        seed_visibility: SeedVisibility,
+        hashes_per_layer: usize,
+    ) -> Result<(ChainLayer, (Vec<Target>, Vec<Target>)), HashChainError> {
+        // Setup the seed target, which the base layer starts the chain from. With a
+        // public seed it doubles as the initial hash; otherwise the initial hash is
+        // the seed commitment, the tag, or absent. A tag is set on the base layer and
+        // inherited from the inner proof after that, like the seed itself.
+        let seed_target = H::add_virtual_state(builder);
+        let (initial_hash_target, salt_target) = match seed_visibility {
+            SeedVisibility::Public => (seed_target.clone(), Vec::new()),
+            SeedVisibility::Committed => (
+                builder.add_virtual_targets(NUM_HASH_OUT_ELTS),
+                builder.add_virtual_targets(SEED_SALT_WIDTH),
+            ),
+            SeedVisibility::Hidden => (Vec::new(), Vec::new()),
+            SeedVisibility::Tagged => (builder.add_virtual_targets(SEED_SALT_WIDTH), Vec::new()),
+        };
+
+        // Set a condition flag to determine if we are in the base case or not.
+        let condition = builder.add_virtual_bool_target_safe();
+
+        // Insert the updateable hash gates of a layer into the circuit, so that we can
+        // update them as we recurse. The base case applies no hash, so that a chain
+        // of n steps is exactly n / hashes_per_layer recursive layers on top of it.
+        let current_hash_in = H::add_virtual_state(builder);
+        let current_hash_out =
+            apply_hashes::<F, D, H>(builder, current_hash_in.clone(), hashes_per_layer);
+        let latest_hash: Vec<Target> = current_hash_out
+            .iter()
+            .zip(&seed_target)
+            .map(|(hash_out, seed)| builder.select(condition, *hash_out, *seed))
+            .collect();
+
+        // The base layer checks the seed against its commitment. Recursive layers
+        // inherit the commitment from the inner proof instead.
+        if seed_visibility == SeedVisibility::Committed {
+            let commitment = seed_commitment_circuit(builder, &seed_target, &salt_target);
+            for (initial, committed) in initial_hash_target.iter().zip(commitment) {
+                let expected = builder.select(condition, *initial, committed);
+                builder.connect(*initial, expected);
+            }
+        }
+
+        // The initial hash, the latest hash and the number of hash applications so
+        // far make up the chain inputs of the layer.
+        let layer = ChainLayer {
+            public_inputs: ChainPublicInputs {
+                initial_hash: initial_hash_target,
+                final_hash: latest_hash,
+                steps: builder.add_virtual_target(),
+                verifier_digest: Vec::new(),
+            },
+            condition,
+            current_hash_in,
+            hashes_per_layer,
+        };
+        Ok((layer, (seed_target, salt_target)))
+    }
+
+    // Set the chain proven by `prove_chain`.
+    pub(crate) fn with_chain(
+        mut self,
+        seed: H::State,
+        salt: [F; SEED_SALT_WIDTH],
+        steps: usize,
+    ) -> Self {
+        self.seed = seed;
+        self.salt = salt;
+        self.steps = steps;
+        self
+    }
+
+    /// Prove the chain configured on the `HashChainBuilder` this prover came from:
+    /// its `steps` hashes starting from its seed.
+    pub fn prove_chain(&self) -> Result<Proof<F, C, D>, HashChainError> {
+        self.prove_with_salt(self.seed.clone(), self.salt, self.steps)
+    }
+
+    /// Like `prove_chain`, also returning telemetry of the circuit and the proving.
+    pub fn prove_chain_with_telemetry(
+        &self,
+    ) -> Result<(Proof<F, C, D>, ChainTelemetry), HashChainError> {
+        self.prove_layers_with_telemetry(self.seed.clone(), self.salt, self.steps)
+    }
+
+    /// Prove a chain of `steps` hashes starting from `seed`. The circuit is reused,
+    /// so this only pays for the proving itself. Chains with a committed or tagged
+    /// seed need a salt, and are proven with `prove_with_salt` instead. `steps` must
+    /// be a multiple of the hashes per layer, and at least one unless the seed is
+    /// public.
+    pub fn prove(&self, seed: H::State, steps: usize) -> Result<Proof<F, C, D>, HashChainError> {
+        self.check_unsalted()?;
+        self.prove_with_salt(seed, [F::ZERO; SEED_SALT_WIDTH], steps)
+    }
+
+    /// Like `prove`, also returning telemetry of the circuit and the proving.
+    pub fn prove_with_telemetry(
+        &self,
+        seed: H::State,
+        steps: usize,
+    ) -> Result<(Proof<F, C, D>, ChainTelemetry), HashChainError> {
+        self.check_unsalted()?;
+        self.prove_layers_with_telemetry(seed, [F::ZERO; SEED_SALT_WIDTH], steps)
+    }
+
+    /// Like `prove`, committing to the seed with `salt`, or publishing it as the tag
+    /// of a tagged chain. The salt is ignored otherwise.
+    pub fn prove_with_salt(
+        &self,
+        seed: H::State,
+        salt: [F; SEED_SALT_WIDTH],
+        steps: usize,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        Ok(self.prove_layers(seed, salt, steps)?.0)
+    }
+
+    // Prove a chain one layer at a time, timing each layer, the base layer first.
+    fn prove_layers(
+        &self,
+        seed: H::State,
+        salt: [F; SEED_SALT_WIDTH],
+        steps: usize,
+    ) -> Result<(Proof<F, C, D>, Vec<Duration>), HashChainError> {
+        self.check_private_steps(steps)?;
+        let layers = self.layers(steps)?;
+        let private_witness = self.private_witness(&seed, &salt);
+        let mut layer_proving_times = Vec::with_capacity(layers + 1);
+
+        let start_time = Instant::now();
+        let mut proof = <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::process_recursive_layer(
+            self.circuit.condition,
+            &self.circuit.inner_cyclic_proof_with_pub_inputs,
+            &self.circuit.data.common,
+            &self.circuit.data,
+            &self.circuit.verifier_data_target,
+            &self.initial_hash_inputs(&seed, &salt),
+            &private_witness,
+            0,
+        )?;
+        layer_proving_times.push(start_time.elapsed());
+        for _ in 0..layers {
+            let start_time = Instant::now();
+            proof = <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::check_cyclic_proof_layer(
+                self.circuit.condition,
+                self.circuit.inner_cyclic_proof_with_pub_inputs.clone(),
+                proof,
+                self.circuit.verifier_data_target.clone(),
+                &self.circuit.data,
+                &private_witness,
+            )?;
+            layer_proving_times.push(start_time.elapsed());
+        }
+        Ok((proof, layer_proving_times))
+    }
+
+    // Prove a chain and gather the telemetry of the circuit and the proving.
+    fn prove_layers_with_telemetry(
+        &self,
+        seed: H::State,
+        salt: [F; SEED_SALT_WIDTH],
+        steps: usize,
+    ) -> Result<(Proof<F, C, D>, ChainTelemetry), HashChainError> {
+        let (proof, layer_proving_times) = self.prove_layers(seed, salt, steps)?;
+        let telemetry = ChainTelemetry::new(
+            &self.circuit.data,
+            self.circuit_build_time,
+            &proof,
+            layer_proving_times,
+            self.hashes_per_layer,
+            steps as u64,
+        )?;
+        Ok((proof, telemetry))
+    }
+
+    // The initial hash public inputs of chains from `seed` under this prover's seed
+    // visibility.
+    pub(crate) fn initial_hash_inputs(
+        &self,
+        seed: &H::State,
+        salt: &[F; SEED_SALT_WIDTH],
+    ) -> Vec<F> {
+        match self.seed_visibility {
+            SeedVisibility::Public => H::state_to_public_inputs(seed),
+            SeedVisibility::Committed => seed_commitment::<F, D, H>(seed, salt).to_vec(),
+            SeedVisibility::Hidden => Vec::new(),
+            SeedVisibility::Tagged => salt.to_vec(),
+        }
+    }
+
+    // Witness for the seed and salt targets. A public seed is carried by the inner
+    // proof and needs none.
+    fn private_witness(&self, seed: &H::State, salt: &[F; SEED_SALT_WIDTH]) -> PartialWitness<F> {
+        let mut pw = PartialWitness::new();
+        if self.seed_visibility != SeedVisibility::Public {
+            let seed_inputs = H::state_to_public_inputs(seed);
+            for (target, value) in self.seed_target.iter().zip(seed_inputs) {
+                pw.set_target(*target, value);
+            }
+            for (target, value) in self.salt_target.iter().zip(salt) {
+                pw.set_target(*target, *value);
+            }
+        }
+        pw
+    }
+
+    /// How the seed of this prover's chains appears in their public inputs.
+    pub fn seed_visibility(&self) -> SeedVisibility {
+        self.seed_visibility
+    }
+
+    /// The number of hashes each recursion layer of this prover applies.
+    pub fn hashes_per_layer(&self) -> usize {
+        self.hashes_per_layer
+    }
+
+    // The number of recursion layers that prove `steps` hashes.
+    pub(crate) fn layers(&self, steps: usize) -> Result<usize, HashChainError> {
+        if steps % self.hashes_per_layer != 0 {
+            return Err(anyhow::Error::msg(format!(
+                "Chain of {} steps is not a multiple of {} hashes per layer.",
+                steps, self.hashes_per_layer
+            ))
+            .into());
+        }
+        Ok(steps / self.hashes_per_layer)
+    }
+
+    // The base layer passes the seed through as its latest hash, so a private seed
+    // would be published as the final hash of a chain of no steps.
+    fn check_private_steps(&self, steps: usize) -> Result<(), HashChainError> {
+        if steps == 0 && self.seed_visibility != SeedVisibility::Public {
+            return Err(HashChainError::EmptyPrivateChain);
+        }
+        Ok(())
+    }
+
+    // Committed and tagged chains publish a hash of the salt or the salt itself, so
+    // they must not silently fall back to the all-zero salt.
+    fn check_unsalted(&self) -> Result<(), HashChainError> {
+        match self.seed_visibility {
+            SeedVisibility::Committed | SeedVisibility::Tagged => Err(HashChainError::MissingSalt),
+            SeedVisibility::Public | SeedVisibility::Hidden => Ok(()),
+        }
+    }
+
+    /// Extend a chain proof of this prover by `k` more steps, a multiple of the hashes
+    /// per layer and at least one unless the seed is public. The proof is fed back
+    /// in as the inner proof of the cyclic circuit, so the history it covers is not
+    /// proven again. The proof is checked first, so that a proof of another circuit
+    /// fails here rather than deep inside the prover.
+    pub fn extend(
+        &self,
+        proof: Proof<F, C, D>,
+        k: usize,
+    ) -> Result<Proof<F, C, D>, HashChainError> {
+        self.check_private_steps(k)?;
+        let layers = self.layers(k)?;
+        check_cyclic_proof_verifier_data(
+            &proof,
+            &self.circuit.data.verifier_only,
+            &self.circuit.data.common,
+        )?;
+        self.circuit.data.verify(proof.clone())?;
+
+        // The seed only enters the base layer, so extending does not need it.
+        let private_witness =
+            self.private_witness(&H::State::default(), &[F::ZERO; SEED_SALT_WIDTH]);
+        let mut proof = proof;
+        for _ in 0..layers {
+            proof = <CircuitBuilder<F, D> as HashChain<F, D, C, H>>::check_cyclic_proof_layer(
+                self.circuit.condition,
+                self.circuit.inner_cyclic_proof_with_pub_inputs.clone(),
+                proof,
+                self.circuit.verifier_data_target.clone(),
+                &self.circuit.data,
+                &private_witness,
+            )?;
+        }
+        Ok(proof)
+    }
+
+    pub fn into_circuit_data(self) -> CircuitMap<F, C, D> {
+        self.circuit.data
+    }
+}
+
+impl<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + 'static,
+        const D: usize,
+        H: ChainHasher<F, D>,
+    > CyclicProver<F, C, D> for HashChainProver<F, C, D, H>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    fn cyclic_circuit(&self) -> &CyclicCircuit<F, C, D> {
+        &self.circuit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        hasher::ChainHasher,
+        prover::{HashChainProver, MAX_HASHES_PER_LAYER},
+        seed_from_bytes, ChainPublicInputs, CyclicProver, HashChain, PoseidonHasher,
+    };
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+
+    #[test]
+    fn test_prover_reuse() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let prover = HashChainProver::<F, C, D>::new().unwrap();
+        let verifier = prover.verifier();
+
+        let seed_a = seed_from_bytes::<F>(b"first chain");
+        let seed_b = seed_from_bytes::<F>(b"second chain");
+        let proof_a = prover.prove(seed_a, 1).unwrap();
+        let proof_b = prover.prove(seed_b, 3).unwrap();
+
+        let public_inputs_a =
+            ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof_a).unwrap();
+        let public_inputs_b =
+            ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof_b).unwrap();
+        assert_eq!(public_inputs_a.initial_hash, seed_a);
+        assert_eq!(public_inputs_b.initial_hash, seed_b);
+        assert_eq!(public_inputs_a.step_count(), 1);
+        assert_eq!(public_inputs_b.step_count(), 3);
+        // Proofs of the same circuit carry the same verifier digest.
+        assert_eq!(
+            public_inputs_a.verifier_digest,
+            public_inputs_b.verifier_digest
+        );
+
+        // Both proofs are of the same circuit.
+        assert!(verifier.verify(proof_a.clone()).is_ok());
+        assert!(verifier.verify(proof_b).is_ok());
+        let result = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+            PoseidonHasher,
+        >>::audit_verify(proof_a, prover.circuit_data());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_extend() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let prover = HashChainProver::<F, C, D>::new().unwrap();
+        let seed = seed_from_bytes::<F>(b"growing chain");
+
+        // Yesterday's proof, extended by today's work, attests to the same chain as
+        // proving the whole history at once.
+        let yesterday = prover.prove(seed, 1).unwrap();
+        let today = prover.extend(yesterday, 2).unwrap();
+        let from_scratch = prover.prove(seed, 3).unwrap();
+        assert_eq!(today.public_inputs, from_scratch.public_inputs);
+        assert!(prover.verifier().verify(today.clone()).is_ok());
+
+        // A proof that does not verify cannot be extended.
+        let mut tampered = today;
+        tampered.public_inputs[4] += F::ONE;
+        assert!(prover.extend(tampered, 1).is_err());
+    }
+
+    #[test]
+    fn test_hashes_per_layer() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        assert!(HashChainProver::<F, C, D>::with_hashes_per_layer(config.clone(), 0).is_err());
+        assert!(HashChainProver::<F, C, D>::with_hashes_per_layer(
+            config.clone(),
+            MAX_HASHES_PER_LAYER + 1
+        )
+        .is_err());
+
+        // Two layers of four hashes each prove a chain of eight steps.
+        let prover = HashChainProver::<F, C, D>::with_hashes_per_layer(config, 4).unwrap();
+        let seed = seed_from_bytes::<F>(b"batched chain");
+        let proof = prover.prove(seed, 8).unwrap();
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof).unwrap();
+        assert_eq!(
+            public_inputs.final_hash,
+            <PoseidonHasher as ChainHasher<F, D>>::iterate(seed, 8)
+        );
+        assert_eq!(public_inputs.step_count(), 8);
+        assert!(prover.verifier().verify(proof.clone()).is_ok());
+
+        // Chains are proven in whole layers.
+        assert!(prover.prove(seed, 6).is_err());
+        let proof = prover.extend(proof, 4).unwrap();
+        assert_eq!(
+            ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof)
+                .unwrap()
+                .step_count(),
+            12
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Layout of the public inputs of a chain proof.
+//!
+//! Every cyclic chain proof carries, in order:
+//!
+//! ```text
+//! [0..I]         initial hash
+//! [I..I + W]     final (latest) hash
+//! [I + W]        steps, the number of hash applications so far
+//! [I + W + 1..]  verifier digest: the circuit digest (4 elements) followed by the
+//!                constants_sigmas_cap of the cyclic circuit, as appended by
+//!                `add_verifier_data_public_inputs`
+//! ```
+//!
+//! where `W` is `ChainHasher::STATE_WIDTH` and `I` depends on the `SeedVisibility`
+//! of the chain: `W` for a public seed, 4 for a seed commitment and 0 for a hidden
+//! seed. Decoding a proof infers `I` from the number of public inputs.
+//!
+//! `ChainPublicInputs` is the single description of this layout: over field
+//! elements it decodes proofs, over `Target`s it is what the circuits register and
+//! connect, so the prover and the verifier cannot disagree on where a value lives.
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::{RichField, NUM_HASH_OUT_ELTS},
+    iop::target::Target,
+    plonk::{
+        circuit_data::VerifierCircuitTarget,
+        config::GenericConfig,
+        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{hasher::ChainHasher, HashChainError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainPublicInputs<T> {
+    pub initial_hash: Vec<T>,
+    pub final_hash: Vec<T>,
+    pub steps: T,
+    pub verifier_digest: Vec<T>,
+}
+
+impl<T: Copy> ChainPublicInputs<T> {
+    /// Split public inputs laid out as described in the module docs, for a chain
+    /// state `width` elements wide. A slice without the verifier digest, such as the
+    /// public inputs of a wrapped proof, yields an empty `verifier_digest`.
+    pub fn from_slice(inputs: &[T], width: usize) -> Result<Self, HashChainError> {
+        Self::from_slice_with_initial_width(inputs, width, width)
+    }
+
+    /// Like `from_slice`, for a chain whose initial hash is `initial_width` elements
+    /// wide rather than a full state, as with a committed or hidden seed.
+    pub fn from_slice_with_initial_width(
+        inputs: &[T],
+        initial_width: usize,
+        width: usize,
+    ) -> Result<Self, HashChainError> {
+        let num_chain_inputs = initial_width + width + 1;
+        if inputs.len() < num_chain_inputs {
+            return Err(HashChainError::PublicInputsLength {
+                expected: num_chain_inputs,
+                actual: inputs.len(),
+            });
+        }
+        Ok(Self {
+            initial_hash: inputs[..initial_width].to_vec(),
+            final_hash: inputs[initial_width..initial_width + width].to_vec(),
+            steps: inputs[initial_width + width],
+            verifier_digest: inputs[num_chain_inputs..].to_vec(),
+        })
+    }
+
+    /// Number of public inputs before the verifier digest, for a chain with a
+    /// public seed.
+    pub fn num_chain_inputs(width: usize) -> usize {
+        2 * width + 1
+    }
+
+    /// The initial hash, final hash and steps, without the verifier digest. This is
+    /// all a wrapped proof exposes.
+    pub fn chain_inputs(&self) -> Vec<T> {
+        let mut inputs = Vec::with_capacity(self.initial_hash.len() + self.final_hash.len() + 1);
+        inputs.extend_from_slice(&self.initial_hash);
+        inputs.extend_from_slice(&self.final_hash);
+        inputs.push(self.steps);
+        inputs
+    }
+
+    // The full layout, in public input order.
+    fn flatten(&self) -> Vec<T> {
+        let mut inputs = self.chain_inputs();
+        inputs.extend_from_slice(&self.verifier_digest);
+        inputs
+    }
+}
+
+impl<F: RichField> ChainPublicInputs<F> {
+    /// Decode the public inputs of a chain proof of `H`.
+    pub fn from_proof<C: GenericConfig<D, F = F>, const D: usize, H: ChainHasher<F, D>>(
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<Self, HashChainError>
+    where
+        F: Extendable<D>,
+    {
+        Self::from_proof_with_width(proof, H::STATE_WIDTH)
+    }
+
+    /// Decode the public inputs of a chain proof whose state is `width` elements
+    /// wide, for chains whose step function is not a `ChainHasher`.
+    pub fn from_proof_with_width<C: GenericConfig<D, F = F>, const D: usize>(
+        proof: &ProofWithPublicInputs<F, C, D>,
+        width: usize,
+    ) -> Result<Self, HashChainError>
+    where
+        F: Extendable<D>,
+    {
+        let initial_width = initial_width(
+            proof.public_inputs.len(),
+            width,
+            verifier_digest_len(proof.proof.wires_cap.0.len()),
+        )?;
+        Self::from_slice_with_initial_width(&proof.public_inputs, initial_width, width)
+    }
+
+    /// Encode back into the public inputs of a proof.
+    pub fn to_public_inputs(&self) -> Vec<F> {
+        self.flatten()
+    }
+
+    /// Number of hash applications the proof attests to.
+    pub fn step_count(&self) -> u64 {
+        self.steps.to_canonical_u64()
+    }
+
+    /// The initial hash as a native chain state of `H`.
+    pub fn initial_state<const D: usize, H: ChainHasher<F, D>>(
+        &self,
+    ) -> Result<H::State, HashChainError>
+    where
+        F: Extendable<D>,
+    {
+        H::state_from_public_inputs(&self.initial_hash)
+    }
+
+    /// The final hash as a native chain state of `H`.
+    pub fn final_state<const D: usize, H: ChainHasher<F, D>>(
+        &self,
+    ) -> Result<H::State, HashChainError>
+    where
+        F: Extendable<D>,
+    {
+        H::state_from_public_inputs(&self.final_hash)
+    }
+}
+
+impl ChainPublicInputs<Target> {
+    /// The public input targets of an inner chain proof of `H`.
+    pub fn from_proof_target<F: RichField + Extendable<D>, const D: usize, H: ChainHasher<F, D>>(
+        proof: &ProofWithPublicInputsTarget<D>,
+    ) -> Result<Self, HashChainError> {
+        let initial_width = initial_width(
+            proof.public_inputs.len(),
+            H::STATE_WIDTH,
+            verifier_digest_len(proof.proof.wires_cap.0.len()),
+        )?;
+        Self::from_slice_with_initial_width(&proof.public_inputs, initial_width, H::STATE_WIDTH)
+    }
+
+    /// The targets in public input order, ready to be registered or connected.
+    pub fn to_targets(&self) -> Vec<Target> {
+        self.flatten()
+    }
+
+    // Point the verifier digest at the targets registered by
+    // `add_verifier_data_public_inputs`.
+    pub(crate) fn set_verifier_digest(&mut self, verifier_data: &VerifierCircuitTarget) {
+        self.verifier_digest = verifier_digest_targets(verifier_data);
+    }
+}
+
+// The verifier data laid out as a verifier digest: the circuit digest followed by
+// the constants_sigmas_cap.
+pub(crate) fn verifier_digest_targets(verifier_data: &VerifierCircuitTarget) -> Vec<Target> {
+    verifier_data
+        .circuit_digest
+        .elements
+        .iter()
+        .chain(
+            verifier_data
+                .constants_sigmas_cap
+                .0
+                .iter()
+                .flat_map(|hash| hash.elements.iter()),
+        )
+        .copied()
+        .collect()
+}
+
+// Number of verifier digest public inputs of a circuit whose Merkle caps hold
+// `cap_len` hashes.
+pub(crate) fn verifier_digest_len(cap_len: usize) -> usize {
+    NUM_HASH_OUT_ELTS * (1 + cap_len)
+}
+
+// Width of the initial hash in `num_public_inputs` public inputs that end with
+// `digest_len` elements of verifier digest, for a chain state `width` elements wide.
+pub(crate) fn initial_width(
+    num_public_inputs: usize,
+    width: usize,
+    digest_len: usize,
+) -> Result<usize, HashChainError> {
+    num_public_inputs.checked_sub(width + 1 + digest_len).ok_or(
+        HashChainError::PublicInputsLength {
+            expected: width + 1 + digest_len,
+            actual: num_public_inputs,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{public_inputs::ChainPublicInputs, HashChainError};
+    use plonky2::field::{
+        goldilocks_field::GoldilocksField,
+        types::{Field, Sample},
+    };
+
+    #[test]
+    fn test_public_inputs_round_trip() {
+        type F = GoldilocksField;
+
+        let inputs = F::rand_vec(4 + 4 + 1 + 8);
+        let public_inputs = ChainPublicInputs::from_slice(&inputs, 4).unwrap();
+        assert_eq!(public_inputs.initial_hash, inputs[0..4]);
+        assert_eq!(public_inputs.final_hash, inputs[4..8]);
+        assert_eq!(public_inputs.steps, inputs[8]);
+        assert_eq!(public_inputs.verifier_digest, inputs[9..]);
+        assert_eq!(public_inputs.to_public_inputs(), inputs);
+        assert_eq!(public_inputs.chain_inputs(), inputs[..9]);
+
+        let json = serde_json::to_string(&public_inputs).unwrap();
+        let decoded: ChainPublicInputs<F> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, public_inputs);
+
+        let wrapped = ChainPublicInputs::from_slice(&public_inputs.chain_inputs(), 4).unwrap();
+        assert!(wrapped.verifier_digest.is_empty());
+
+        let hidden = ChainPublicInputs::from_slice_with_initial_width(&inputs[4..], 0, 4).unwrap();
+        assert!(hidden.initial_hash.is_empty());
+        assert_eq!(hidden.final_hash, inputs[4..8]);
+
+        let short = ChainPublicInputs::from_slice(&[F::ONE; 8], 4);
+        assert!(matches!(
+            short,
+            Err(HashChainError::PublicInputsLength {
+                expected: 9,
+                actual: 8
+            })
+        ));
+    }
+}
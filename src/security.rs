@@ -0,0 +1,244 @@
+//! FRI parameter presets and security estimates.
+//!
+//! The soundness of a plonky2 proof rests mostly on its FRI parameters: every query
+//! round multiplies the chance of catching a cheating prover, by a factor that grows
+//! with the rate, and the proof-of-work grinding adds its bits on top. Raising the
+//! rate makes the prover slower but lets fewer queries, and so a smaller proof,
+//! reach the same security.
+//!
+//! `security_report` estimates the security of a compiled circuit two ways:
+//!
+//! - conjectured: `rate_bits` bits per query, as under the proximity gaps
+//!   conjecture that plonky2's own parameter choices rely on;
+//! - provable: queries counted only up to the Johnson bound, with the error of the
+//!   FRI commit phase bounded as in Ben-Sasson, Carmon, Ishai, Kopparty and Saraf
+//!   (BCIKS20).
+//!
+//! Both are further limited by the size of the field the PLONK challenges are drawn
+//! from.
+use std::fmt;
+
+use plonky2::{
+    field::extension::Extendable,
+    fri::FriConfig,
+    hash::hash_types::RichField,
+    plonk::circuit_data::{CircuitConfig, CommonCircuitData},
+};
+
+// The multiplicity parameter of the Johnson bound. Larger values tighten the query
+// bound but loosen the commit phase bound; 3 balances them for the usual rates.
+const JOHNSON_MULTIPLICITY: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SecurityPreset {
+    /// The smallest blowup plonky2's gates allow, for the fastest prover. Targets
+    /// 100 bits of conjectured security. This is the standard recursion config.
+    #[default]
+    FastProver,
+    /// A high rate and few queries, for small proofs at the cost of a prover about
+    /// sixteen times slower. Targets 100 bits of conjectured security.
+    SmallProof,
+    /// More queries and more grinding than `FastProver`, for 128 bits of
+    /// conjectured FRI security, and a third permutation challenge. Over the
+    /// quadratic extension the quotient check caps the whole at about
+    /// 128 - log2(8n) bits for a circuit of degree n, which `security_report` shows.
+    HighSecurity,
+}
+
+impl SecurityPreset {
+    /// The FRI config of the preset.
+    pub fn fri_config(self) -> FriConfig {
+        let standard = CircuitConfig::standard_recursion_config().fri_config;
+        let (rate_bits, num_query_rounds, proof_of_work_bits, cap_height) = match self {
+            SecurityPreset::FastProver => (3, 28, 16, 4),
+            SecurityPreset::SmallProof => (7, 12, 16, 4),
+            SecurityPreset::HighSecurity => (3, 38, 20, 5),
+        };
+        FriConfig {
+            rate_bits,
+            num_query_rounds,
+            proof_of_work_bits,
+            cap_height,
+            ..standard
+        }
+    }
+
+    /// The standard recursion config with the FRI config of the preset.
+    pub fn circuit_config(self) -> CircuitConfig {
+        let standard = CircuitConfig::standard_recursion_config();
+        let num_challenges = match self {
+            SecurityPreset::HighSecurity => 3,
+            _ => standard.num_challenges,
+        };
+        CircuitConfig {
+            num_challenges,
+            fri_config: self.fri_config(),
+            ..standard
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecurityReport {
+    pub degree_bits: usize,
+    pub rate_bits: usize,
+    pub num_query_rounds: usize,
+    pub proof_of_work_bits: u32,
+    pub cap_height: usize,
+    /// Bits of security of the PLONK checks: the permutation argument over the base
+    /// field, repeated once per challenge, and the quotient check at a point of the
+    /// extension field.
+    pub field_bits: f64,
+    /// `rate_bits` per query plus the proof-of-work bits, limited by `field_bits`.
+    pub conjectured_bits: f64,
+    /// Queries up to the Johnson bound plus the proof-of-work bits, limited by the
+    /// error of the FRI commit phase and by `field_bits`.
+    pub provable_bits: f64,
+}
+
+impl fmt::Display for SecurityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "degree 2^{}, rate 2^-{}, {} queries, {} proof-of-work bits, cap height {}",
+            self.degree_bits,
+            self.rate_bits,
+            self.num_query_rounds,
+            self.proof_of_work_bits,
+            self.cap_height
+        )?;
+        writeln!(f, "field:       {:.1} bits", self.field_bits)?;
+        writeln!(f, "conjectured: {:.1} bits", self.conjectured_bits)?;
+        write!(f, "provable:    {:.1} bits", self.provable_bits)
+    }
+}
+
+/// Estimate the security of proofs of the circuit described by `common`.
+pub fn security_report<F: RichField + Extendable<D>, const D: usize>(
+    common: &CommonCircuitData<F, D>,
+) -> SecurityReport {
+    let fri_config = &common.config.fri_config;
+    let degree_bits = common.degree_bits();
+    let rate_bits = fri_config.rate_bits;
+    let num_query_rounds = fri_config.num_query_rounds;
+    let proof_of_work_bits = fri_config.proof_of_work_bits;
+    let base_field_bits = F::BITS as f64;
+    let extension_field_bits = (F::BITS * D) as f64;
+
+    // A cheating prover passes each permutation challenge with probability about
+    // n / |F|, and the quotient check with about quotient_degree * n / |F^D|.
+    let permutation_bits =
+        common.config.num_challenges as f64 * (base_field_bits - degree_bits as f64);
+    let quotient_bits =
+        extension_field_bits - degree_bits as f64 - (common.quotient_degree_factor as f64).log2();
+    let field_bits = permutation_bits.min(quotient_bits);
+
+    let grinding_bits = proof_of_work_bits as f64;
+    let conjectured_bits =
+        (rate_bits as f64 * num_query_rounds as f64 + grinding_bits).min(field_bits);
+
+    // Up to the Johnson bound a query catches a far codeword except with probability
+    // sqrt(rho) * (1 + 1 / 2m). The commit phase errs with probability at most
+    // (m + 1/2)^7 * n^2 / (3 * rho^(3/2) * |F^D|) over an evaluation domain of size n,
+    // the leading term of the BCIKS20 bound on the FRI commit phase; its second term,
+    // linear in n, is much smaller and left out.
+    let m = JOHNSON_MULTIPLICITY;
+    let query_bits = rate_bits as f64 / 2.0 - (1.0 + 1.0 / (2.0 * m)).log2();
+    let domain_bits = (degree_bits + rate_bits) as f64;
+    let commit_bits = extension_field_bits
+        - (7.0 * (m + 0.5).log2() + 2.0 * domain_bits + 1.5 * rate_bits as f64 - 3.0_f64.log2());
+    let provable_bits = (query_bits * num_query_rounds as f64 + grinding_bits)
+        .min(commit_bits)
+        .min(field_bits);
+
+    SecurityReport {
+        degree_bits,
+        rate_bits,
+        num_query_rounds,
+        proof_of_work_bits,
+        cap_height: fri_config.cap_height,
+        field_bits,
+        conjectured_bits,
+        provable_bits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        security::{security_report, SecurityPreset},
+        seed_from_bytes, ChainHasher, ChainPublicInputs, HashChainBuilder, PoseidonHasher,
+    };
+    use plonky2::{
+        hash::{hash_types::NUM_HASH_OUT_ELTS, poseidon::PoseidonHash},
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+
+    #[test]
+    fn test_security_presets() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let report = |preset: SecurityPreset| {
+            let mut builder = CircuitBuilder::<F, D>::new(preset.circuit_config());
+            let state = builder.add_virtual_targets(4);
+            let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(state);
+            builder.register_public_inputs(&hash.elements);
+            security_report(&builder.build::<C>().common)
+        };
+
+        let fast = report(SecurityPreset::FastProver);
+        let small = report(SecurityPreset::SmallProof);
+        let high = report(SecurityPreset::HighSecurity);
+        assert_eq!(fast.conjectured_bits, 100.0);
+        assert_eq!(small.conjectured_bits, 100.0);
+        assert!(high.conjectured_bits > fast.conjectured_bits);
+        assert_eq!(high.conjectured_bits, high.field_bits);
+        for report in [fast, small, high] {
+            assert!(report.provable_bits < report.conjectured_bits);
+            assert!(report.conjectured_bits <= report.field_bits);
+        }
+        // A higher rate makes each query count for more, even provably.
+        assert!(small.num_query_rounds < fast.num_query_rounds);
+        assert!(small.provable_bits > fast.provable_bits);
+    }
+
+    #[test]
+    fn test_preset_chains() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // The presets change the rate and the cap height, and with them the size of
+        // the recursion common data and of the verifier digest the chain carries.
+        let seed = seed_from_bytes::<F>(b"preset chain");
+        for preset in [SecurityPreset::SmallProof, SecurityPreset::HighSecurity] {
+            let (prover, verifier) = HashChainBuilder::<F, C, D>::new()
+                .security_preset(preset)
+                .seed(seed)
+                .steps(1)
+                .build()
+                .unwrap();
+            assert_eq!(verifier.common().config.fri_config, preset.fri_config());
+
+            let proof = prover.prove_chain().unwrap();
+            let public_inputs =
+                ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof).unwrap();
+            assert_eq!(
+                public_inputs.final_hash,
+                <PoseidonHasher as ChainHasher<F, D>>::iterate(seed, 1)
+            );
+            let cap_len = 1 << preset.fri_config().cap_height;
+            assert_eq!(
+                public_inputs.verifier_digest.len(),
+                NUM_HASH_OUT_ELTS * (1 + cap_len)
+            );
+            assert!(verifier.verify_steps::<PoseidonHasher>(proof, 1).is_ok());
+        }
+    }
+}
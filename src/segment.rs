@@ -0,0 +1,283 @@
+//! Parallel segment proving with 2-to-1 aggregation.
+//!
+//! A single chain proof is strictly sequential: every layer verifies the one before
+//! it. But the prover knows every intermediate hash, so a chain of n steps can be
+//! cut into segments, each proven on its own rayon worker from its intermediate
+//! starting hash. The segment proofs are then merged pairwise in aggregation
+//! circuits, which check that the final hash of the left segment is the initial
+//! hash of the right one and sum their counters, until a single proof of the whole
+//! chain remains. Wall-clock time then scales with the number of cores rather than
+//! with the length of the chain.
+//!
+//! Aggregated proofs expose only the chain inputs of `ChainPublicInputs`: initial
+//! hash, final hash and steps.
+use log::info;
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, CommonCircuitData, VerifierOnlyCircuitData},
+        config::{AlgebraicHasher, GenericConfig},
+        proof::ProofWithPublicInputsTarget,
+    },
+};
+use rayon::prelude::*;
+
+use crate::{
+    hasher::ChainHasher, public_inputs::verifier_digest_targets, zk::SeedVisibility,
+    ChainPublicInputs, CyclicProver, HashChainError, HashChainProver, PoseidonHasher, Proof,
+};
+
+// A circuit that merges two adjacent segment proofs of the level below.
+struct AggregationLevel<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    data: CircuitData<F, C, D>,
+    left: ProofWithPublicInputsTarget<D>,
+    right: ProofWithPublicInputsTarget<D>,
+}
+
+pub struct SegmentedProver<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+    H: ChainHasher<F, D> = PoseidonHasher,
+> {
+    prover: HashChainProver<F, C, D, H>,
+    levels: Vec<AggregationLevel<F, C, D>>,
+}
+
+impl<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F> + 'static,
+        const D: usize,
+        H: ChainHasher<F, D>,
+    > SegmentedProver<F, C, D, H>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Build a prover that cuts chains into `num_segments` segments, with the
+    /// standard recursion config. `num_segments` must be a power of two of at
+    /// least 2.
+    pub fn new(num_segments: usize) -> Result<Self, HashChainError> {
+        Self::with_config(CircuitConfig::standard_recursion_config(), num_segments)
+    }
+
+    /// Build a prover that cuts chains into `num_segments` segments, with `config`
+    /// for the segment circuit and every aggregation level.
+    pub fn with_config(config: CircuitConfig, num_segments: usize) -> Result<Self, HashChainError> {
+        // Fail before building the segment circuit rather than after.
+        Self::check_num_segments(num_segments)?;
+        Self::from_prover(
+            HashChainProver::with_config(config.clone())?,
+            config,
+            num_segments,
+        )
+    }
+
+    /// Build the aggregation levels on top of an existing prover, whose chains must
+    /// have a public seed: every segment but the first starts from an intermediate
+    /// hash that the aggregation has to see. `num_segments` must be a power of two of
+    /// at least 2.
+    pub fn from_prover(
+        prover: HashChainProver<F, C, D, H>,
+        config: CircuitConfig,
+        num_segments: usize,
+    ) -> Result<Self, HashChainError> {
+        Self::check_num_segments(num_segments)?;
+        if prover.seed_visibility() != SeedVisibility::Public {
+            return Err(anyhow::Error::msg("Segmented chains need a public seed.").into());
+        }
+
+        let mut levels: Vec<AggregationLevel<F, C, D>> = Vec::new();
+        for _ in 0..num_segments.trailing_zeros() {
+            let (inner_common, inner_verifier, pin_verifier_data) = match levels.last() {
+                Some(level) => (&level.data.common, &level.data.verifier_only, false),
+                None => (
+                    &prover.circuit_data().common,
+                    &prover.circuit_data().verifier_only,
+                    true,
+                ),
+            };
+            let level = Self::build_level(
+                config.clone(),
+                inner_common,
+                inner_verifier,
+                pin_verifier_data,
+            )?;
+            info!(
+                "Aggregation level {} degree {} = 2^{}",
+                levels.len(),
+                level.data.common.degree(),
+                level.data.common.degree_bits()
+            );
+            levels.push(level);
+        }
+        Ok(Self { prover, levels })
+    }
+
+    // Every aggregation level halves the number of proofs, so only a power of two of
+    // at least 2 segments ends in a single aggregated proof.
+    fn check_num_segments(num_segments: usize) -> Result<(), HashChainError> {
+        if num_segments < 2 || !num_segments.is_power_of_two() {
+            return Err(anyhow::Error::msg(format!(
+                "Number of segments must be a power of two of at least 2, got {}.",
+                num_segments
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    // Build a circuit that verifies two proofs of the inner circuit against a
+    // constant verifier key, checks that they are adjacent segments of one chain and
+    // exposes the chain inputs of their concatenation. When the inner circuit is the
+    // cyclic one, the verifier digests its proofs carry are pinned to that key too.
+    fn build_level(
+        config: CircuitConfig,
+        inner_common: &CommonCircuitData<F, D>,
+        inner_verifier: &VerifierOnlyCircuitData<C, D>,
+        pin_verifier_data: bool,
+    ) -> Result<AggregationLevel<F, C, D>, HashChainError> {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let verifier_data = builder.constant_verifier_data(inner_verifier);
+        let left = builder.add_virtual_proof_with_pis(inner_common);
+        let right = builder.add_virtual_proof_with_pis(inner_common);
+        builder.verify_proof::<C>(&left, &verifier_data, inner_common);
+        builder.verify_proof::<C>(&right, &verifier_data, inner_common);
+
+        let left_inputs = ChainPublicInputs::from_slice(&left.public_inputs, H::STATE_WIDTH)?;
+        let right_inputs = ChainPublicInputs::from_slice(&right.public_inputs, H::STATE_WIDTH)?;
+        if pin_verifier_data {
+            for inputs in [&left_inputs, &right_inputs] {
+                for (actual, expected) in inputs
+                    .verifier_digest
+                    .iter()
+                    .zip(verifier_digest_targets(&verifier_data))
+                {
+                    builder.connect(*actual, expected);
+                }
+            }
+        }
+
+        // The right segment continues where the left one ends.
+        for (left_final, right_initial) in left_inputs
+            .final_hash
+            .iter()
+            .zip(&right_inputs.initial_hash)
+        {
+            builder.connect(*left_final, *right_initial);
+        }
+        let public_inputs = ChainPublicInputs {
+            initial_hash: left_inputs.initial_hash,
+            final_hash: right_inputs.final_hash,
+            steps: builder.add(left_inputs.steps, right_inputs.steps),
+            verifier_digest: Vec::new(),
+        };
+        builder.register_public_inputs(&public_inputs.chain_inputs());
+
+        let data = builder.build::<C>();
+        Ok(AggregationLevel { data, left, right })
+    }
+
+    /// Prove a chain of `steps` hashes starting from `seed`. The segments are proven
+    /// in parallel, and so are the aggregations within each level. `steps` must be a
+    /// multiple of the hashes per layer of the segment prover.
+    pub fn prove(&self, seed: H::State, steps: usize) -> Result<Proof<F, C, D>, HashChainError> {
+        // Cut the chain into segments of nearly equal numbers of layers, and find the
+        // hash each of them starts from.
+        let layers = self.prover.layers(steps)?;
+        let num_segments = 1 << self.levels.len();
+        let mut segments = Vec::with_capacity(num_segments);
+        let mut start = seed;
+        for i in 0..num_segments {
+            let length = (layers / num_segments + usize::from(i < layers % num_segments))
+                * self.prover.hashes_per_layer();
+            let next = H::iterate(start.clone(), length);
+            segments.push((start, length));
+            start = next;
+        }
+
+        let mut proofs = segments
+            .into_par_iter()
+            .map(|(start, length)| self.prover.prove(start, length))
+            .collect::<Result<Vec<_>, _>>()?;
+        for level in &self.levels {
+            proofs = proofs
+                .par_chunks(2)
+                .map(|pair| {
+                    let mut pw = PartialWitness::new();
+                    pw.set_proof_with_pis_target(&level.left, &pair[0]);
+                    pw.set_proof_with_pis_target(&level.right, &pair[1]);
+                    Ok(level.data.prove(pw)?)
+                })
+                .collect::<Result<Vec<_>, HashChainError>>()?;
+        }
+        proofs
+            .pop()
+            .ok_or_else(|| anyhow::Error::msg("No segment proofs to aggregate.").into())
+    }
+
+    /// Verify an aggregated proof against the last aggregation level.
+    pub fn verify(&self, proof: Proof<F, C, D>) -> Result<(), HashChainError> {
+        Ok(self.final_circuit()?.verify(proof)?)
+    }
+
+    /// The circuit that produces aggregated proofs. Its verifier data is all a
+    /// verifier of aggregated proofs needs.
+    pub fn final_circuit(&self) -> Result<&CircuitData<F, C, D>, HashChainError> {
+        self.levels
+            .last()
+            .map(|level| &level.data)
+            .ok_or_else(|| anyhow::Error::msg("Segmented prover has no aggregation level.").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        hasher::ChainHasher, seed_from_bytes, segment::SegmentedProver, ChainPublicInputs,
+        HashChainProver, PoseidonHasher,
+    };
+    use plonky2::{
+        field::types::Field,
+        plonk::{
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+
+    #[test]
+    fn test_segmented_proof() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        assert!(SegmentedProver::<F, C, D>::new(3).is_err());
+        // Building on an existing prover checks the number of segments just the same.
+        for num_segments in [0, 1, 6] {
+            let segment_prover = HashChainProver::<F, C, D>::new().unwrap();
+            let config = CircuitConfig::standard_recursion_config();
+            assert!(SegmentedProver::from_prover(segment_prover, config, num_segments).is_err());
+        }
+
+        // Four segments of 2, 1, 1 and 1 steps, merged over two levels.
+        let prover = SegmentedProver::<F, C, D>::new(4).unwrap();
+        let seed = seed_from_bytes::<F>(b"segmented chain");
+        let proof = prover.prove(seed, 5).unwrap();
+
+        let public_inputs = ChainPublicInputs::from_slice(&proof.public_inputs, 4).unwrap();
+        assert_eq!(public_inputs.initial_hash, seed);
+        assert_eq!(
+            public_inputs.final_hash,
+            <PoseidonHasher as ChainHasher<F, D>>::iterate(seed, 5)
+        );
+        assert_eq!(public_inputs.step_count(), 5);
+        assert!(prover.verify(proof.clone()).is_ok());
+
+        let mut tampered = proof;
+        tampered.public_inputs[8] += F::ONE;
+        assert!(prover.verify(tampered).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! SHA-256 hash chain built on the `plonky2_crypto` SHA-256 gadget.
+//!
+//! The chain state is a 32-byte SHA-256 digest, exposed as eight big-endian `u32`
+//! words in digest order (the usual `H0..H7` representation). The state occupies
+//! public inputs `[0..8]` and `[8..16]` and the counter sits at `[16]`.
+use plonky2::{
+    field::extension::Extendable, hash::hash_types::RichField, iop::target::Target,
+    plonk::circuit_builder::CircuitBuilder,
+};
+use plonky2_crypto::{
+    hash::{sha256::CircuitBuilderHashSha2, CircuitBuilderHash},
+    u32::arithmetic_u32::{CircuitBuilderU32, U32Target},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{hasher::ChainHasher, HashChainError};
+
+/// Number of `u32` words in a SHA-256 digest.
+pub const SHA256_DIGEST_WORDS: usize = 8;
+
+// Size of a single SHA-256 message block. A 32-byte state plus padding always fits
+// in one block.
+const SHA256_BLOCK_BITS: usize = 512;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl<F: RichField + Extendable<D>, const D: usize> ChainHasher<F, D> for Sha256Hasher {
+    type State = [u8; 32];
+
+    const STATE_WIDTH: usize = SHA256_DIGEST_WORDS;
+
+    // Range check the words, so that a seed cannot carry values that are not `u32`s
+    // into the public inputs.
+    fn add_virtual_state(builder: &mut CircuitBuilder<F, D>) -> Vec<Target> {
+        let words = builder.add_virtual_u32_targets(SHA256_DIGEST_WORDS);
+        builder.range_check_u32(words.clone());
+        word_targets(&words)
+    }
+
+    // Apply SHA-256 to a 32-byte state given as big-endian words. `plonky2_crypto`
+    // packs the padded block as one big-endian integer, so limb `i` holds message
+    // word `15 - i` and output limb `i` holds digest word `7 - i`. The block is
+    // constrained to the state followed by its fixed padding: a single 1 bit and the
+    // 256-bit length.
+    fn hash_circuit(builder: &mut CircuitBuilder<F, D>, state: &[Target]) -> Vec<Target> {
+        let input = builder.add_virtual_hash_input_target(1, SHA256_BLOCK_BITS);
+        let num_limbs = input.input.limbs.len();
+        for (i, limb) in input.input.limbs.iter().enumerate() {
+            match state.get(num_limbs - 1 - i) {
+                Some(state_word) => builder.connect(limb.0, *state_word),
+                None => {
+                    let padding = match i {
+                        0 => (SHA256_DIGEST_WORDS * 32) as u32,
+                        i if i == SHA256_DIGEST_WORDS - 1 => 0x8000_0000,
+                        _ => 0,
+                    };
+                    let padding = builder.constant_u32(padding);
+                    builder.connect_u32(*limb, padding);
+                }
+            }
+        }
+        let no_more_blocks = builder._false();
+        for block in &input.blocks {
+            builder.connect(block.target, no_more_blocks.target);
+        }
+        let mut hash_out = word_targets(&builder.hash_sha256(&input).limbs);
+        hash_out.reverse();
+        hash_out
+    }
+
+    fn hash(state: &Self::State) -> Self::State {
+        Sha256::digest(state).into()
+    }
+
+    fn state_to_public_inputs(state: &Self::State) -> Vec<F> {
+        sha256_digest_to_words::<F>(state).to_vec()
+    }
+
+    fn state_from_public_inputs(inputs: &[F]) -> Result<Self::State, HashChainError> {
+        let words: [F; SHA256_DIGEST_WORDS] = inputs.try_into()?;
+        sha256_words_to_digest(&words)
+    }
+}
+
+fn word_targets(words: &[U32Target]) -> Vec<Target> {
+    words.iter().map(|word| word.0).collect()
+}
+
+/// Encode a SHA-256 digest as the eight big-endian `u32` words used for the chain's
+/// public inputs.
+pub fn sha256_digest_to_words<F: RichField>(digest: &[u8; 32]) -> [F; SHA256_DIGEST_WORDS] {
+    std::array::from_fn(|i| {
+        let word = u32::from_be_bytes(digest[4 * i..4 * i + 4].try_into().unwrap());
+        F::from_canonical_u32(word)
+    })
+}
+
+/// Decode eight big-endian `u32` words from the chain's public inputs back into a
+/// SHA-256 digest, failing on words that do not fit in a `u32`.
+pub fn sha256_words_to_digest<F: RichField>(words: &[F]) -> Result<[u8; 32], HashChainError> {
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(words) {
+        let value = word.to_canonical_u64();
+        let word = u32::try_from(value).map_err(|_| HashChainError::LimbOutOfRange { value })?;
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    Ok(digest)
+}
+
+/// Iterate SHA-256 n number of times, the native counterpart of the SHA-256 chain.
+pub fn iterate_sha256(initial_state: [u8; 32], n: usize) -> [u8; 32] {
+    let mut current = initial_state;
+    for _ in 0..n {
+        current = Sha256::digest(current).into();
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        sha256::{iterate_sha256, sha256_words_to_digest, Sha256Hasher},
+        HashChain, HashChainError,
+    };
+    use plonky2::{
+        field::{
+            goldilocks_field::GoldilocksField,
+            types::{Field, PrimeField64},
+        },
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_sha256_hash_chain() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let seed: [u8; 32] = Sha256::digest(b"sha256 hash chain seed").into();
+        let config = CircuitConfig::standard_recursion_config();
+        let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
+        let (proof, circuit_map) =
+            <CircuitBuilder<GoldilocksField, D> as HashChain<
+                GoldilocksField,
+                D,
+                C,
+                Sha256Hasher,
+            >>::build_hash_chain_circuit_with_seed(&mut circuit, seed, 2)
+            .unwrap();
+
+        assert_eq!(
+            sha256_words_to_digest(&proof.public_inputs[0..8]).unwrap(),
+            seed
+        );
+
+        // Check the circuit output against the sha2 crate directly.
+        let mut expected = seed;
+        for _ in 0..proof.public_inputs[16].to_canonical_u64() {
+            expected = Sha256::digest(expected).into();
+        }
+        assert_eq!(
+            sha256_words_to_digest(&proof.public_inputs[8..16]).unwrap(),
+            expected
+        );
+
+        let result = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+            Sha256Hasher,
+        >>::audit_verify(proof, &circuit_map);
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn test_iterate_sha256() {
+        assert_eq!(
+            hex::encode(iterate_sha256([0u8; 32], 1)),
+            "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925"
+        );
+        assert_eq!(
+            iterate_sha256(iterate_sha256([7u8; 32], 3), 2),
+            iterate_sha256([7u8; 32], 5)
+        );
+    }
+
+    #[test]
+    fn test_word_out_of_range() {
+        let mut words = [GoldilocksField::ZERO; 8];
+        words[3] = GoldilocksField::from_canonical_u64(1 << 32);
+        assert!(matches!(
+            sha256_words_to_digest(&words),
+            Err(HashChainError::LimbOutOfRange { value }) if value == 1 << 32
+        ));
+
+        words[3] = GoldilocksField::from_canonical_u32(u32::MAX);
+        let digest = sha256_words_to_digest(&words).unwrap();
+        assert_eq!(digest[12..16], [0xff; 4]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Persisting proofs and circuit data to disk.
+//!
+//! Proofs are stored in plonky2's own byte encoding. Circuit data is encoded with
+//! `DefaultGateSerializer` and, where the prover data is included,
+//! `DefaultGeneratorSerializer`. The default serializers only know about plonky2's
+//! built-in gates and generators, which covers the Poseidon chain; circuits using
+//! the `plonky2_crypto` gadgets need their gates registered in a custom serializer.
+use std::{fs, marker::PhantomData, path::Path};
+
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    plonk::{
+        circuit_data::{CircuitData, CommonCircuitData, VerifierOnlyCircuitData},
+        config::{AlgebraicHasher, GenericConfig},
+        proof::ProofWithPublicInputs,
+    },
+    util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer},
+};
+
+use crate::HashChainError;
+
+/// Write a proof to `path`.
+pub fn save_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    path: impl AsRef<Path>,
+) -> Result<(), HashChainError> {
+    Ok(fs::write(path, proof.to_bytes())?)
+}
+
+/// Read a proof from `path`. The common data of the circuit that produced it is
+/// needed to decode the proof.
+pub fn load_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    path: impl AsRef<Path>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<ProofWithPublicInputs<F, C, D>, HashChainError> {
+    let bytes = fs::read(path)?;
+    Ok(ProofWithPublicInputs::from_bytes(bytes, common_data)?)
+}
+
+/// Write the verifier-only data of a circuit to `path`.
+pub fn save_verifier_data<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    path: impl AsRef<Path>,
+) -> Result<(), HashChainError> {
+    let bytes = verifier_data
+        .to_bytes()
+        .map_err(|_| HashChainError::SerializationError("VerifierOnlyCircuitData"))?;
+    Ok(fs::write(path, bytes)?)
+}
+
+/// Read the verifier-only data of a circuit from `path`.
+pub fn load_verifier_data<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    path: impl AsRef<Path>,
+) -> Result<VerifierOnlyCircuitData<C, D>, HashChainError> {
+    let bytes = fs::read(path)?;
+    VerifierOnlyCircuitData::from_bytes(bytes)
+        .map_err(|_| HashChainError::SerializationError("VerifierOnlyCircuitData"))
+}
+
+/// Write the common data of a circuit to `path`.
+pub fn save_common_data<F: RichField + Extendable<D>, const D: usize>(
+    common_data: &CommonCircuitData<F, D>,
+    path: impl AsRef<Path>,
+) -> Result<(), HashChainError> {
+    let bytes = common_data
+        .to_bytes(&DefaultGateSerializer)
+        .map_err(|_| HashChainError::SerializationError("CommonCircuitData"))?;
+    Ok(fs::write(path, bytes)?)
+}
+
+/// Read the common data of a circuit from `path`.
+pub fn load_common_data<F: RichField + Extendable<D>, const D: usize>(
+    path: impl AsRef<Path>,
+) -> Result<CommonCircuitData<F, D>, HashChainError> {
+    let bytes = fs::read(path)?;
+    CommonCircuitData::from_bytes(bytes, &DefaultGateSerializer)
+        .map_err(|_| HashChainError::SerializationError("CommonCircuitData"))
+}
+
+/// Write the full circuit data, including the prover data, to `path`, so that the
+/// circuit does not need to be rebuilt to prove again.
+pub fn save_circuit_data<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    const D: usize,
+>(
+    circuit_data: &CircuitData<F, C, D>,
+    path: impl AsRef<Path>,
+) -> Result<(), HashChainError>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+        _phantom: PhantomData,
+    };
+    let bytes = circuit_data
+        .to_bytes(&DefaultGateSerializer, &generator_serializer)
+        .map_err(|_| HashChainError::SerializationError("CircuitData"))?;
+    Ok(fs::write(path, bytes)?)
+}
+
+/// Read the full circuit data from `path`.
+pub fn load_circuit_data<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    const D: usize,
+>(
+    path: impl AsRef<Path>,
+) -> Result<CircuitData<F, C, D>, HashChainError>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+        _phantom: PhantomData,
+    };
+    let bytes = fs::read(path)?;
+    CircuitData::from_bytes(&bytes, &DefaultGateSerializer, &generator_serializer)
+        .map_err(|_| HashChainError::SerializationError("CircuitData"))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        storage::{
+            load_common_data, load_proof, load_verifier_data, save_common_data, save_proof,
+            save_verifier_data,
+        },
+        HashChain,
+    };
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField,
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::{CircuitConfig, VerifierCircuitData},
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+        recursion::cyclic_recursion::check_cyclic_proof_verifier_data,
+    };
+    use std::fs;
+
+    #[test]
+    fn test_verify_from_files() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let dir = std::env::temp_dir().join(format!("hash-chain-storage-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let proof_path = dir.join("proof.bin");
+        let verifier_path = dir.join("verifier_only.bin");
+        let common_path = dir.join("common.bin");
+
+        // Prove, then write everything a verifier needs to disk.
+        {
+            let config = CircuitConfig::standard_recursion_config();
+            let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
+            let (proof, circuit_map) = <CircuitBuilder<GoldilocksField, D> as HashChain<
+                GoldilocksField,
+                D,
+                C,
+            >>::build_hash_chain_circuit(&mut circuit, 2)
+            .unwrap();
+            save_proof(&proof, &proof_path).unwrap();
+            save_verifier_data::<F, C, D>(&circuit_map.verifier_only, &verifier_path).unwrap();
+            save_common_data(&circuit_map.common, &common_path).unwrap();
+        }
+
+        // Verify from the files alone.
+        let common = load_common_data::<F, D>(&common_path).unwrap();
+        let verifier_only = load_verifier_data::<F, C, D>(&verifier_path).unwrap();
+        let proof = load_proof::<F, C, D>(&proof_path, &common).unwrap();
+        check_cyclic_proof_verifier_data(&proof, &verifier_only, &common).unwrap();
+        let verifier_data = VerifierCircuitData {
+            verifier_only,
+            common,
+        };
+        assert!(verifier_data.verify(proof).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+//! Structured telemetry for chain proofs.
+//!
+//! `HashChainProver::prove_with_telemetry` returns a `ChainTelemetry` alongside the
+//! proof, describing the shape and size of the cyclic circuit and where the proving
+//! time went. It serializes with serde, for instance to JSON for dashboards and for
+//! tracking regressions across runs.
+use std::{collections::BTreeMap, time::Duration};
+
+use plonky2::{
+    field::{extension::Extendable, types::PrimeField64},
+    hash::hash_types::RichField,
+    plonk::{circuit_data::CircuitData, config::GenericConfig},
+    util::serialization::DefaultGateSerializer,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{HashChainError, Proof};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChainTelemetry {
+    /// Number of rows of each gate type in the cyclic circuit, padding included, by
+    /// gate id.
+    pub gate_counts: BTreeMap<String, usize>,
+    /// Log2 of the number of rows of the cyclic circuit.
+    pub degree_bits: usize,
+    /// Size of the common circuit data, serialized with `DefaultGateSerializer`.
+    pub common_data_bytes: usize,
+    /// Size of the uncompressed proof.
+    pub proof_bytes: usize,
+    /// Time taken to build the cyclic circuit, recursion common data included.
+    pub circuit_build_time: Duration,
+    /// Time taken to prove each layer, the base layer first.
+    pub layer_proving_times: Vec<Duration>,
+    /// Number of hashes each recursive layer applies.
+    pub hashes_per_layer: usize,
+    /// Number of hash applications the proof covers.
+    pub steps: u64,
+}
+
+impl ChainTelemetry {
+    /// Telemetry of `proof`, a proof of `steps` hashes of the cyclic circuit
+    /// `circuit_data`, which took `circuit_build_time` to build and
+    /// `layer_proving_times` to prove.
+    pub fn new<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+        circuit_data: &CircuitData<F, C, D>,
+        circuit_build_time: Duration,
+        proof: &Proof<F, C, D>,
+        layer_proving_times: Vec<Duration>,
+        hashes_per_layer: usize,
+        steps: u64,
+    ) -> Result<Self, HashChainError> {
+        let common_data_bytes = circuit_data
+            .common
+            .to_bytes(&DefaultGateSerializer)
+            .map_err(|_| HashChainError::SerializationError("CommonCircuitData"))?
+            .len();
+        Ok(Self {
+            gate_counts: gate_counts(circuit_data),
+            degree_bits: circuit_data.common.degree_bits(),
+            common_data_bytes,
+            proof_bytes: proof.to_bytes().len(),
+            circuit_build_time,
+            layer_proving_times,
+            hashes_per_layer,
+            steps,
+        })
+    }
+
+    /// Total time taken to prove every layer.
+    pub fn proving_time(&self) -> Duration {
+        self.layer_proving_times.iter().sum()
+    }
+
+    /// Hash applications proven per second of proving time.
+    pub fn hashes_per_second(&self) -> f64 {
+        self.steps as f64 / self.proving_time().as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Count the rows of each gate type in a compiled circuit. The builder's gate
+/// instances are gone once the circuit is built, but the selector polynomials
+/// remember them: a row evaluates to the index of its gate in the selector of the
+/// group holding that gate.
+pub fn gate_counts<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    circuit_data: &CircuitData<F, C, D>,
+) -> BTreeMap<String, usize> {
+    let common = &circuit_data.common;
+    let selectors_info = &common.selectors_info;
+    let mut counts = vec![0; common.gates.len()];
+    if selectors_info.num_selectors() == 0 {
+        // A single gate type needs no selector, and fills every row.
+        counts.fill(common.degree());
+    }
+    let selectors = &circuit_data
+        .prover_only
+        .constants_sigmas_commitment
+        .polynomials[..selectors_info.num_selectors()];
+    for (group, selector) in selectors_info.groups.iter().zip(selectors) {
+        for value in selector.clone().fft().values {
+            let index = value.to_canonical_u64() as usize;
+            if group.contains(&index) {
+                counts[index] += 1;
+            }
+        }
+    }
+    common
+        .gates
+        .iter()
+        .zip(counts)
+        .map(|(gate, count)| (gate.0.id(), count))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{seed_from_bytes, telemetry::ChainTelemetry, CyclicProver, HashChainProver};
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_chain_telemetry() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let prover = HashChainProver::<F, C, D>::new().unwrap();
+        let seed = seed_from_bytes::<F>(b"telemetry");
+        let (proof, telemetry) = prover.prove_with_telemetry(seed, 2).unwrap();
+        assert!(prover.verifier().verify(proof).is_ok());
+
+        // Every row of the circuit holds exactly one gate.
+        assert_eq!(
+            telemetry.gate_counts.values().sum::<usize>(),
+            1 << telemetry.degree_bits
+        );
+        assert!(telemetry
+            .gate_counts
+            .iter()
+            .any(|(id, count)| id.starts_with("PoseidonGate") && *count > 0));
+        assert_eq!(telemetry.steps, 2);
+        // The base layer and one layer per step.
+        assert_eq!(telemetry.layer_proving_times.len(), 3);
+        assert!(telemetry.proof_bytes > 0 && telemetry.common_data_bytes > 0);
+
+        let json = serde_json::to_string(&telemetry).unwrap();
+        let decoded: ChainTelemetry = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, telemetry);
+    }
+}
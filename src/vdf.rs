@@ -0,0 +1,178 @@
+//! Hash chains as a verifiable delay function.
+//!
+//! The verifier picks a challenge, which is hashed into the seed under a domain tag
+//! of its own, so that VDF outputs can never be confused with chains proven for any
+//! other purpose. The prover then computes the output H^T(seed) with T sequential
+//! Poseidon evaluations and proves it with a `HashChainProver`.
+//! Checking the proof with `verify` is cheap, while no amount of parallelism lets
+//! the prover get to the output faster than T evaluations.
+use std::time::{Duration, Instant};
+
+use log::info;
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::{RichField, NUM_HASH_OUT_ELTS},
+    plonk::config::{AlgebraicHasher, GenericConfig},
+};
+
+use crate::{
+    hasher::ChainHasher, prover::HashChainProver, seed_from_bytes, verifier::HashChainVerifier,
+    zk::SeedVisibility, ChainPublicInputs, HashChainError, PoseidonHasher, Proof,
+};
+
+/// Domain tag prepended to every challenge before it is hashed into a seed.
+pub const VDF_DOMAIN: &[u8] = b"hash-chain/vdf/poseidon/v1";
+
+pub struct VdfEvaluation<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    /// H^T(seed) for the seed derived from the challenge.
+    pub output: [F; NUM_HASH_OUT_ELTS],
+    /// Proof that `output` is T sequential evaluations away from the seed.
+    pub proof: Proof<F, C, D>,
+    /// Time taken by the T sequential evaluations alone.
+    pub sequential_time: Duration,
+    /// Time taken to prove the evaluations, excluding the circuit build.
+    pub proving_time: Duration,
+}
+
+/// The seed of the VDF for `challenge`.
+pub fn vdf_seed<F: RichField>(challenge: &[u8]) -> [F; NUM_HASH_OUT_ELTS] {
+    seed_from_bytes(&[VDF_DOMAIN, challenge].concat())
+}
+
+/// Evaluate the VDF on `challenge` with `t` sequential Poseidon evaluations, and
+/// prove the result with `prover`, whose seed must be public. The returned
+/// evaluation also reports how long the evaluations took on their own against how
+/// long proving them took. The circuit is built once with the prover, so repeated
+/// evaluations do not pay for it again.
+pub fn vdf_eval<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    const D: usize,
+>(
+    prover: &HashChainProver<F, C, D>,
+    challenge: &[u8],
+    t: usize,
+) -> Result<VdfEvaluation<F, C, D>, HashChainError>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    // The verifier checks the seed, so a prover with a private one is of no use.
+    if prover.seed_visibility() != SeedVisibility::Public {
+        return Err(anyhow::Error::msg("VDF proofs need a public seed.").into());
+    }
+    let seed = vdf_seed::<F>(challenge);
+
+    let start_time = Instant::now();
+    let output = <PoseidonHasher as ChainHasher<F, D>>::iterate(seed, t);
+    let sequential_time = start_time.elapsed();
+
+    let start_time = Instant::now();
+    let proof = prover.prove(seed, t)?;
+    let proving_time = start_time.elapsed();
+    debug_assert_eq!(
+        ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof)?.final_hash,
+        output
+    );
+
+    info!(
+        "VDF of {} evaluations: sequential {:?}, proving {:?} ({:.1}x)",
+        t,
+        sequential_time,
+        proving_time,
+        proving_time.as_secs_f64() / sequential_time.as_secs_f64().max(f64::EPSILON)
+    );
+    Ok(VdfEvaluation {
+        output,
+        proof,
+        sequential_time,
+        proving_time,
+    })
+}
+
+/// Verify that `output` is the VDF of `challenge` with `t` sequential evaluations,
+/// as attested by `proof`. The verifier must be derived independently of the
+/// evaluator, for instance from `HashChainProver::new`, so that the proof is checked
+/// against the Poseidon chain circuit and not one of the evaluator's choosing.
+pub fn vdf_verify<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    const D: usize,
+>(
+    challenge: &[u8],
+    t: u64,
+    output: &[F; NUM_HASH_OUT_ELTS],
+    proof: Proof<F, C, D>,
+    verifier: &HashChainVerifier<F, C, D>,
+) -> Result<(), HashChainError>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    let public_inputs = ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof)?;
+    if public_inputs.initial_hash != vdf_seed::<F>(challenge) {
+        return Err(HashChainError::ChallengeMismatch);
+    }
+    if public_inputs.final_hash != output {
+        return Err(HashChainError::VdfOutputMismatch);
+    }
+    verifier.verify_steps::<PoseidonHasher>(proof, t)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        seed_from_bytes,
+        vdf::{vdf_eval, vdf_seed, vdf_verify},
+        CyclicProver, HashChainError, HashChainProver,
+    };
+    use plonky2::{
+        field::types::Field,
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+
+    #[test]
+    fn test_vdf() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let challenge = b"block 1234 beacon";
+        // The seed is domain separated from a chain seeded with the bare challenge.
+        assert_ne!(vdf_seed::<F>(challenge), seed_from_bytes::<F>(challenge));
+
+        let prover = HashChainProver::<F, C, D>::new().unwrap();
+        let evaluation = vdf_eval(&prover, challenge, 3).unwrap();
+        let output = evaluation.output;
+        // The verifier builds the chain circuit itself rather than trusting one from
+        // the evaluator.
+        let verifier = HashChainProver::<F, C, D>::new().unwrap().verifier();
+        assert!(vdf_verify(challenge, 3, &output, evaluation.proof.clone(), &verifier).is_ok());
+
+        assert!(matches!(
+            vdf_verify(
+                b"another beacon",
+                3,
+                &output,
+                evaluation.proof.clone(),
+                &verifier
+            ),
+            Err(HashChainError::ChallengeMismatch)
+        ));
+        let mut wrong_output = output;
+        wrong_output[0] += F::ONE;
+        assert!(matches!(
+            vdf_verify(
+                challenge,
+                3,
+                &wrong_output,
+                evaluation.proof.clone(),
+                &verifier
+            ),
+            Err(HashChainError::VdfOutputMismatch)
+        ));
+        assert!(matches!(
+            vdf_verify(challenge, 4, &output, evaluation.proof, &verifier),
+            Err(HashChainError::StepCountMismatch { .. })
+        ));
+    }
+}
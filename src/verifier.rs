@@ -0,0 +1,192 @@
+//! Standalone verifier for chain proofs.
+//!
+//! `HashChain::verify` takes the full `CircuitData`, prover key included, which
+//! means rebuilding the whole cyclic circuit just to check a proof. A
+//! `HashChainVerifier` only holds the verifier-only data and the common data, both
+//! of which can be loaded from bytes, so light clients never compile the prover
+//! circuit.
+use std::path::Path;
+
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    plonk::{
+        circuit_data::{
+            CircuitData, CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+        },
+        config::{AlgebraicHasher, GenericConfig},
+        proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs},
+    },
+    recursion::cyclic_recursion::check_cyclic_proof_verifier_data,
+    util::serialization::DefaultGateSerializer,
+};
+
+use crate::{
+    hasher::ChainHasher,
+    security::{security_report, SecurityReport},
+    storage::{load_common_data, load_verifier_data},
+    ChainPublicInputs, HashChainError,
+};
+
+pub struct HashChainVerifier<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    verifier_data: VerifierCircuitData<F, C, D>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    HashChainVerifier<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Build a verifier from the verifier-only and common data of a cyclic circuit.
+    pub fn new(
+        verifier_only: VerifierOnlyCircuitData<C, D>,
+        common: CommonCircuitData<F, D>,
+    ) -> Self {
+        Self {
+            verifier_data: VerifierCircuitData {
+                verifier_only,
+                common,
+            },
+        }
+    }
+
+    /// Build a verifier from the byte encodings of the verifier-only data and the
+    /// common data, the latter encoded with `DefaultGateSerializer`.
+    pub fn from_bytes(
+        verifier_only_bytes: Vec<u8>,
+        common_bytes: Vec<u8>,
+    ) -> Result<Self, HashChainError> {
+        let verifier_only = VerifierOnlyCircuitData::from_bytes(verifier_only_bytes)
+            .map_err(|_| HashChainError::SerializationError("VerifierOnlyCircuitData"))?;
+        let common = CommonCircuitData::from_bytes(common_bytes, &DefaultGateSerializer)
+            .map_err(|_| HashChainError::SerializationError("CommonCircuitData"))?;
+        Ok(Self::new(verifier_only, common))
+    }
+
+    /// Build a verifier from files written by `storage::save_verifier_data` and
+    /// `storage::save_common_data`.
+    pub fn from_files(
+        verifier_only_path: impl AsRef<Path>,
+        common_path: impl AsRef<Path>,
+    ) -> Result<Self, HashChainError> {
+        let verifier_only = load_verifier_data::<F, C, D>(verifier_only_path)?;
+        let common = load_common_data::<F, D>(common_path)?;
+        Ok(Self::new(verifier_only, common))
+    }
+
+    /// Extract the verifier half of the circuit data of a prover.
+    pub fn from_circuit_data(cyclic_circuit_data: &CircuitData<F, C, D>) -> Self {
+        Self::new(
+            cyclic_circuit_data.verifier_only.clone(),
+            cyclic_circuit_data.common.clone(),
+        )
+    }
+
+    /// Verify a chain proof. Besides the proof itself, the verifier data the proof
+    /// carries in its public inputs must match this verifier's, otherwise the
+    /// recursion could have verified a different circuit.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<(), HashChainError> {
+        check_cyclic_proof_verifier_data(
+            &proof,
+            &self.verifier_data.verifier_only,
+            &self.verifier_data.common,
+        )?;
+        Ok(self.verifier_data.verify(proof)?)
+    }
+
+    /// Verify a chain proof of `H` and check that it covers exactly `steps` hash
+    /// applications.
+    pub fn verify_steps<H: ChainHasher<F, D>>(
+        &self,
+        proof: ProofWithPublicInputs<F, C, D>,
+        steps: u64,
+    ) -> Result<(), HashChainError> {
+        let actual = ChainPublicInputs::from_proof::<C, D, H>(&proof)?.step_count();
+        if actual != steps {
+            return Err(HashChainError::StepCountMismatch {
+                expected: steps,
+                actual,
+            });
+        }
+        self.verify(proof)
+    }
+
+    /// Verify a compressed chain proof, see `verify`.
+    pub fn verify_compressed(
+        &self,
+        compressed_proof: CompressedProofWithPublicInputs<F, C, D>,
+    ) -> Result<(), HashChainError> {
+        let proof = compressed_proof.decompress(
+            &self.verifier_data.verifier_only.circuit_digest,
+            &self.verifier_data.common,
+        )?;
+        self.verify(proof)
+    }
+
+    pub fn verifier_only(&self) -> &VerifierOnlyCircuitData<C, D> {
+        &self.verifier_data.verifier_only
+    }
+
+    pub fn common(&self) -> &CommonCircuitData<F, D> {
+        &self.verifier_data.common
+    }
+
+    /// Estimate the security of the proofs this verifier accepts.
+    pub fn security_report(&self) -> SecurityReport {
+        security_report(&self.verifier_data.common)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{verifier::HashChainVerifier, HashChain, PoseidonHasher};
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+        util::serialization::DefaultGateSerializer,
+    };
+
+    #[test]
+    fn test_standalone_verifier() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
+        let (proof, circuit_map) = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+        >>::build_hash_chain_circuit(&mut circuit, 2)
+        .unwrap();
+
+        // Hand the verifier nothing but bytes, then drop the prover circuit.
+        let verifier_only_bytes = circuit_map.verifier_only.to_bytes().unwrap();
+        let common_bytes = circuit_map.common.to_bytes(&DefaultGateSerializer).unwrap();
+        drop(circuit_map);
+
+        let verifier =
+            HashChainVerifier::<F, C, D>::from_bytes(verifier_only_bytes, common_bytes).unwrap();
+        assert!(verifier.verify(proof.clone()).is_ok());
+        assert!(verifier
+            .verify_steps::<PoseidonHasher>(proof.clone(), 2)
+            .is_ok());
+        assert!(verifier
+            .verify_steps::<PoseidonHasher>(proof.clone(), 3)
+            .is_err());
+
+        let mut tampered_proof = proof;
+        tampered_proof.public_inputs[4] += F::ONE;
+        assert!(verifier.verify(tampered_proof).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Shrinking wrapper for final chain proofs.
+//!
+//! The cyclic circuit has to be large enough to verify itself, and it exposes its
+//! own verifier data as public inputs. The wrapper recursively verifies the final
+//! cyclic proof inside a sequence of smaller circuits, each built with a config
+//! tuned for proof size, and the last of them exposes only the chain's public
+//! inputs: initial hash, final hash and counter.
+use log::info;
+use plonky2::{
+    field::extension::Extendable,
+    fri::{reduction_strategies::FriReductionStrategy, FriConfig},
+    hash::hash_types::RichField,
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, CommonCircuitData, VerifierOnlyCircuitData},
+        config::{AlgebraicHasher, GenericConfig},
+        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
+};
+
+use crate::{
+    hasher::ChainHasher,
+    public_inputs::{initial_width, verifier_digest_len, verifier_digest_targets},
+    ChainPublicInputs, CircuitMap, HashChainError, Proof,
+};
+
+// A single wrapping circuit together with the target its inner proof is bound to.
+struct WrapperStage<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    data: CircuitData<F, C, D>,
+    proof_target: ProofWithPublicInputsTarget<D>,
+}
+
+pub struct ShrinkWrapper<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    stages: Vec<WrapperStage<F, C, D>>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    ShrinkWrapper<F, C, D>
+where
+    <C as GenericConfig<D>>::Hasher: AlgebraicHasher<F>,
+{
+    /// Build a wrapper for proofs of the given cyclic circuit using the default
+    /// stages from `default_configs`.
+    pub fn new<H: ChainHasher<F, D>>(
+        cyclic_circuit_data: &CircuitMap<F, C, D>,
+    ) -> Result<Self, HashChainError> {
+        Self::with_configs::<H>(cyclic_circuit_data, &Self::default_configs())
+    }
+
+    /// Build a wrapper for proofs of the given cyclic circuit, with one wrapping
+    /// circuit per config.
+    pub fn with_configs<H: ChainHasher<F, D>>(
+        cyclic_circuit_data: &CircuitMap<F, C, D>,
+        configs: &[CircuitConfig],
+    ) -> Result<Self, HashChainError> {
+        if configs.is_empty() {
+            return Err(anyhow::Error::msg("Shrink wrapper needs at least one stage.").into());
+        }
+
+        let mut stages: Vec<WrapperStage<F, C, D>> = Vec::with_capacity(configs.len());
+        for config in configs {
+            let (inner_common, inner_verifier, pin_verifier_data) = match stages.last() {
+                Some(stage) => (&stage.data.common, &stage.data.verifier_only, false),
+                None => (
+                    &cyclic_circuit_data.common,
+                    &cyclic_circuit_data.verifier_only,
+                    true,
+                ),
+            };
+            let stage = Self::build_stage(
+                config.clone(),
+                inner_common,
+                inner_verifier,
+                H::STATE_WIDTH,
+                pin_verifier_data,
+            )?;
+            info!(
+                "Wrapper stage {} degree {} = 2^{}",
+                stages.len(),
+                stage.data.common.degree(),
+                stage.data.common.degree_bits()
+            );
+            stages.push(stage);
+        }
+        Ok(Self { stages })
+    }
+
+    /// The default stages: one standard recursion layer to shed the cyclic
+    /// verifier, one high-rate layer, and a final layer with FRI parameters chosen
+    /// for the smallest proof. Each targets roughly 100 bits of conjectured security.
+    pub fn default_configs() -> Vec<CircuitConfig> {
+        let standard_config = CircuitConfig::standard_recursion_config();
+        let high_rate_config = CircuitConfig {
+            fri_config: FriConfig {
+                rate_bits: 7,
+                proof_of_work_bits: 16,
+                num_query_rounds: 12,
+                ..standard_config.fri_config.clone()
+            },
+            ..standard_config.clone()
+        };
+        let final_config = CircuitConfig {
+            fri_config: FriConfig {
+                cap_height: 0,
+                reduction_strategy: FriReductionStrategy::MinSize(None),
+                ..high_rate_config.fri_config.clone()
+            },
+            ..high_rate_config.clone()
+        };
+        vec![standard_config, high_rate_config, final_config]
+    }
+
+    // Build a circuit that verifies a proof of the inner circuit against a constant
+    // verifier key and re-exposes its chain public inputs, for a chain state `width`
+    // elements wide. When wrapping the cyclic circuit, the verifier digest it carries
+    // in its public inputs is also pinned to that key, which is the in-circuit
+    // equivalent of `check_cyclic_proof_verifier_data`.
+    fn build_stage(
+        config: CircuitConfig,
+        inner_common: &CommonCircuitData<F, D>,
+        inner_verifier: &VerifierOnlyCircuitData<C, D>,
+        width: usize,
+        pin_verifier_data: bool,
+    ) -> Result<WrapperStage<F, C, D>, HashChainError> {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_target = builder.add_virtual_proof_with_pis(inner_common);
+        // The cyclic circuit appends its verifier digest; the stages after it expose
+        // only the chain inputs. What remains in front of the final hash is the
+        // initial hash, however wide the seed visibility made it.
+        let digest_len = if pin_verifier_data {
+            verifier_digest_len(inner_common.config.fri_config.num_cap_elements())
+        } else {
+            0
+        };
+        let initial_width = initial_width(inner_common.num_public_inputs, width, digest_len)?;
+        let inner_public_inputs = ChainPublicInputs::from_slice_with_initial_width(
+            &proof_target.public_inputs,
+            initial_width,
+            width,
+        )?;
+        let verifier_data = builder.constant_verifier_data(inner_verifier);
+        builder.verify_proof::<C>(&proof_target, &verifier_data, inner_common);
+
+        if pin_verifier_data {
+            for (actual, expected) in inner_public_inputs
+                .verifier_digest
+                .iter()
+                .zip(verifier_digest_targets(&verifier_data))
+            {
+                builder.connect(*actual, expected);
+            }
+        }
+
+        builder.register_public_inputs(&inner_public_inputs.chain_inputs());
+        let data = builder.build::<C>();
+        Ok(WrapperStage { data, proof_target })
+    }
+
+    /// Wrap a final chain proof, returning a proof of the last stage that carries
+    /// only the chain's public inputs.
+    pub fn prove(&self, proof: Proof<F, C, D>) -> Result<Proof<F, C, D>, HashChainError> {
+        let mut proof = proof;
+        for stage in &self.stages {
+            let mut pw = PartialWitness::new();
+            pw.set_proof_with_pis_target(&stage.proof_target, &proof);
+            proof = stage.data.prove(pw)?;
+        }
+        info!("Wrapped Proof length: {} bytes", proof.to_bytes().len());
+        Ok(proof)
+    }
+
+    /// Verify a wrapped proof against the last stage.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<(), HashChainError> {
+        Ok(self.final_circuit().verify(proof)?)
+    }
+
+    /// The circuit that produces wrapped proofs. Its verifier data is all a
+    /// verifier of wrapped proofs needs.
+    pub fn final_circuit(&self) -> &CircuitData<F, C, D> {
+        &self
+            .stages
+            .last()
+            .expect("a shrink wrapper always has at least one stage")
+            .data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{wrap::ShrinkWrapper, ChainPublicInputs, HashChain, PoseidonHasher};
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField,
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+    };
+
+    #[test]
+    fn test_shrink_wrapped_hash_chain() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut circuit = CircuitBuilder::<F, D>::new(config.clone());
+        let (proof, circuit_map) = <CircuitBuilder<GoldilocksField, D> as HashChain<
+            GoldilocksField,
+            D,
+            C,
+        >>::build_hash_chain_circuit(&mut circuit, 2)
+        .unwrap();
+
+        let wrapper = ShrinkWrapper::<F, C, D>::new::<PoseidonHasher>(&circuit_map).unwrap();
+        let wrapped_proof = wrapper.prove(proof.clone()).unwrap();
+
+        // Only the initial hash, final hash and counter survive the wrapping.
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof).unwrap();
+        assert_eq!(wrapped_proof.public_inputs, public_inputs.chain_inputs());
+        assert!(wrapped_proof.to_bytes().len() < proof.to_bytes().len());
+        assert!(wrapper.verify(wrapped_proof).is_ok());
+    }
+}
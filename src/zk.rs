@@ -0,0 +1,177 @@
+//! Zero-knowledge chains.
+//!
+//! With a public seed anyone can recompute every intermediate hash, so blinding the
+//! circuit alone hides nothing. In zero-knowledge mode the cyclic circuit is built
+//! with `zero_knowledge: true` and the seed is a private witness of the base layer.
+//! It is then either published as a salted Poseidon commitment or not at all, and a
+//! proof shows "I know x with H^n(x) = y" for the public final hash y and step
+//! count n.
+use plonky2::{
+    field::extension::Extendable,
+    hash::{
+        hash_types::{RichField, NUM_HASH_OUT_ELTS},
+        hashing::hash_n_to_hash_no_pad,
+        poseidon::{PoseidonHash, PoseidonPermutation},
+    },
+    iop::target::Target,
+    plonk::circuit_builder::CircuitBuilder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::hasher::ChainHasher;
+
+/// Number of field elements in the salt of a seed commitment.
+pub const SEED_SALT_WIDTH: usize = 4;
+
+/// How the seed of a chain appears in its public inputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeedVisibility {
+    /// The initial hash public inputs are the seed itself.
+    #[default]
+    Public,
+    /// The initial hash public inputs are `seed_commitment(seed, salt)`.
+    Committed,
+    /// The seed does not appear in the public inputs at all.
+    Hidden,
+    /// The seed does not appear in the public inputs, and the initial hash public
+    /// inputs are the salt instead. The salt then serves as a public tag that ties
+    /// the chain to a context, such as an enrollment, and that extending the chain
+    /// cannot change.
+    Tagged,
+}
+
+impl SeedVisibility {
+    /// Number of initial hash public inputs of a chain whose state is `width`
+    /// elements wide.
+    pub fn initial_hash_width(&self, width: usize) -> usize {
+        match self {
+            SeedVisibility::Public => width,
+            SeedVisibility::Committed => NUM_HASH_OUT_ELTS,
+            SeedVisibility::Hidden => 0,
+            SeedVisibility::Tagged => SEED_SALT_WIDTH,
+        }
+    }
+}
+
+/// Salted Poseidon commitment to a seed of `H`, as published by chains with
+/// `SeedVisibility::Committed`. The salt keeps low-entropy seeds from being found by
+/// brute force, and should be random unless the seed already is.
+pub fn seed_commitment<F: RichField + Extendable<D>, const D: usize, H: ChainHasher<F, D>>(
+    seed: &H::State,
+    salt: &[F; SEED_SALT_WIDTH],
+) -> [F; NUM_HASH_OUT_ELTS] {
+    let mut inputs = H::state_to_public_inputs(seed);
+    inputs.extend_from_slice(salt);
+    hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(&inputs).elements
+}
+
+// In-circuit counterpart of `seed_commitment`.
+pub(crate) fn seed_commitment_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    seed: &[Target],
+    salt: &[Target],
+) -> Vec<Target> {
+    let inputs = seed.iter().chain(salt).copied().collect();
+    builder
+        .hash_n_to_hash_no_pad::<PoseidonHash>(inputs)
+        .elements
+        .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        zk::{seed_commitment, SeedVisibility},
+        ChainHasher, ChainPublicInputs, HashChainBuilder, HashChainError, PoseidonHasher,
+    };
+    use plonky2::{
+        field::types::{Field, Sample},
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    };
+
+    #[test]
+    fn test_hidden_seed() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let seed = [F::rand(), F::rand(), F::rand(), F::rand()];
+        let (prover, verifier) = HashChainBuilder::<F, C, D>::new()
+            .zero_knowledge(true)
+            .seed(seed)
+            .steps(2)
+            .build()
+            .unwrap();
+        assert!(verifier.common().config.zero_knowledge);
+
+        let proof = prover.prove_chain().unwrap();
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof).unwrap();
+
+        // Only the final hash, the step count and the verifier digest are public.
+        assert!(public_inputs.initial_hash.is_empty());
+        assert!(!public_inputs.to_public_inputs().contains(&seed[0]));
+        let expected = <PoseidonHasher as ChainHasher<F, D>>::iterate(seed, 2);
+        assert_eq!(public_inputs.final_hash, expected);
+        assert!(verifier
+            .verify_steps::<PoseidonHasher>(proof.clone(), 2)
+            .is_ok());
+
+        // The chain still grows without the seed.
+        let proof = prover.extend(proof, 1).unwrap();
+        assert!(verifier
+            .verify_steps::<PoseidonHasher>(proof.clone(), 3)
+            .is_ok());
+
+        // A chain of no steps would publish the seed as its final hash.
+        assert!(matches!(
+            prover.prove(seed, 0),
+            Err(HashChainError::EmptyPrivateChain)
+        ));
+        assert!(matches!(
+            prover.extend(proof, 0),
+            Err(HashChainError::EmptyPrivateChain)
+        ));
+    }
+
+    #[test]
+    fn test_committed_seed() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let seed = [F::rand(), F::rand(), F::rand(), F::rand()];
+        let salt = [F::rand(), F::rand(), F::rand(), F::rand()];
+        let (prover, verifier) = HashChainBuilder::<F, C, D>::new()
+            .zero_knowledge(true)
+            .seed_visibility(SeedVisibility::Committed)
+            .seed(seed)
+            .salt(salt)
+            .steps(1)
+            .build()
+            .unwrap();
+
+        let proof = prover.prove_chain().unwrap();
+        let public_inputs = ChainPublicInputs::from_proof::<C, D, PoseidonHasher>(&proof).unwrap();
+        assert_eq!(
+            public_inputs.initial_hash,
+            seed_commitment::<F, D, PoseidonHasher>(&seed, &salt)
+        );
+        assert_ne!(public_inputs.initial_hash, seed);
+        assert!(verifier.verify_steps::<PoseidonHasher>(proof, 1).is_ok());
+
+        // A committed seed never falls back to the all-zero salt.
+        assert!(matches!(
+            prover.prove(seed, 1),
+            Err(HashChainError::MissingSalt)
+        ));
+        assert!(matches!(
+            HashChainBuilder::<F, C, D>::new()
+                .zero_knowledge(true)
+                .seed_visibility(SeedVisibility::Committed)
+                .seed(seed)
+                .build(),
+            Err(HashChainError::MissingSalt)
+        ));
+    }
+}